@@ -0,0 +1,53 @@
+//! Compares [`AsciiIdentifier`] against an equivalent char-by-char loop over a [`ParseStream`].
+//!
+//! Run with `cargo bench --bench ascii_identifier`.
+
+use std::{hint::black_box, time::Instant};
+
+use quoth::{parsable::AsciiIdentifier, *};
+
+const ITERATIONS: usize = 200;
+
+fn parse_char_by_char(stream: &mut ParseStream) -> Result<Span> {
+    let start_position = stream.mark();
+    match stream.peek_char() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => stream.position += 1,
+        _ => return Err(Error::new(stream.current_span(), "expected identifier")),
+    }
+    while let Some(c) = stream.peek_char() {
+        if !c.is_ascii_alphanumeric() && c != '_' {
+            break;
+        }
+        stream.position += 1;
+    }
+    Ok(stream.span_from(start_position))
+}
+
+fn bench(name: &str, input: &str, mut parse: impl FnMut(&mut ParseStream) -> bool) {
+    let mut stream = ParseStream::from(input);
+    let start = Instant::now();
+    let mut count = 0;
+    for _ in 0..ITERATIONS {
+        stream.position = 0;
+        while parse(&mut stream) {
+            count += 1;
+            stream.position += 1;
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{name:>14}: {:>10.2?} total, {:>8.2?} per identifier",
+        elapsed,
+        elapsed / black_box(count).max(1) as u32
+    );
+}
+
+fn main() {
+    let input = "some_identifier another_one x y123 _private CONSTANT_NAME ".repeat(1_000);
+    bench("AsciiIdentifier", &input, |stream| {
+        black_box(stream.parse::<AsciiIdentifier>()).is_ok()
+    });
+    bench("char-by-char", &input, |stream| {
+        black_box(parse_char_by_char(stream)).is_ok()
+    });
+}
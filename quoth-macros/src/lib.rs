@@ -1,10 +1,29 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
-use quote::{quote, ToTokens};
-use syn::{parse2, spanned::Spanned, Error, Item, Result};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse2, spanned::Spanned, Error, Field, Fields, GenericArgument, Item, ItemEnum, ItemStruct,
+    LitStr, Member, PathArguments, Result, Type, Variant,
+};
 
-/// Derives [`Display`](core::fmt::Display) and [`FromStr`](core::str::FromStr) based on the
-/// the `parse()` and `unparse()` implementations of `Parsable` for this type, respectively.
+/// Derives [`FromStr`](core::str::FromStr) and [`Display`](core::fmt::Display) based on the
+/// `parse()` and `unparse()` implementations of `Parsable` for this type, respectively.
+///
+/// `Parsable` requires both of these traits, so this derive is the intended way to satisfy
+/// them for your own parsable types. Exactly two impls are generated:
+///
+/// - `FromStr`, with `Err = quoth::Error`, which parses the string via `quoth::parse`. Note
+///   that, like `quoth::parse`, this does not require the entire string to be consumed.
+/// - `Display`, which delegates to `Parsable::unparse` (by default the source text of the
+///   type's span).
+///
+/// The type must implement `Parsable` itself (by hand or via `#[derive(Parsable)]`), along
+/// with the other supertraits of `Parsable` (`Clone`, `Debug`, `PartialEq`, `Eq`, `Hash`, and
+/// `Spanned`, the latter typically via `#[derive(Spanned)]`). `Peekable` is then provided by a
+/// blanket impl and needs no derive. The generated code refers to the `quoth` crate by name,
+/// so within `quoth` itself add `use crate as quoth;`.
+///
+/// See the documentation of `Parsable` for complete examples.
 #[proc_macro_derive(ParsableExt)]
 pub fn derive_parsable_ext(tokens: TokenStream) -> TokenStream {
     match derive_parsable_ext_internal(tokens.into()) {
@@ -42,7 +61,17 @@ fn derive_parsable_ext_internal(tokens: TokenStream2) -> Result<TokenStream2> {
 
 /// Automatically derives `Spanned` for the annotated type. This will work as long as there is
 /// some struct field of type `Span`.
-#[proc_macro_derive(Spanned)]
+///
+/// If the type has more than one field of type `Span` (e.g. the spans of an opening and a
+/// closing delimiter), `span()` returns all of them joined together, in keeping with the
+/// `Spanned` contract. Spans that can't be joined because they come from a different `Source`
+/// than the first one are left out. To use a single field instead, mark it with
+/// `#[quoth(primary)]` (or the equivalent `#[quoth(span)]`), which can also designate a field
+/// whose type is an alias of `Span`.
+///
+/// For enums, each variant must either contain a field of type `Span`, contain exactly one
+/// field (whose own `span()` is used), or be a unit variant (which has a blank span).
+#[proc_macro_derive(Spanned, attributes(quoth))]
 pub fn derive_spanned(tokens: TokenStream) -> TokenStream {
     match derive_spanned_internal(tokens.into()) {
         Ok(tokens) => tokens,
@@ -53,48 +82,482 @@ pub fn derive_spanned(tokens: TokenStream) -> TokenStream {
 
 fn derive_spanned_internal(tokens: TokenStream2) -> Result<TokenStream2> {
     let item = parse2::<Item>(tokens)?;
-    let (field_name, ident, generics) = match item {
-        // Item::Enum(item_enum) => (item_enum.ident, item_enum.generics),
+    let (body, ident, generics) = match item {
+        Item::Enum(item_enum) => {
+            let mut arms = Vec::new();
+            for variant in &item_enum.variants {
+                let variant_ident = &variant.ident;
+                if variant.fields.is_empty() {
+                    arms.push(quote!(Self::#variant_ident { .. } => quoth::Span::blank()));
+                    continue;
+                }
+                let members = span_members(&variant.fields)?;
+                if !members.is_empty() {
+                    let vars: Vec<_> = (0..members.len())
+                        .map(|i| format_ident!("__span_{}", i))
+                        .collect();
+                    let joined = join_spans(&vars);
+                    arms.push(quote! {
+                        Self::#variant_ident { #(#members: #vars),*, .. } => #joined
+                    });
+                } else if variant.fields.len() == 1 {
+                    let member = field_member(variant.fields.iter().next().unwrap(), 0);
+                    arms.push(quote! {
+                        Self::#variant_ident { #member: inner, .. } => quoth::Spanned::span(inner)
+                    });
+                } else {
+                    return Err(Error::new(
+                        variant.span(),
+                        "expected a single field or a field of type `quoth::Span`",
+                    ));
+                }
+            }
+            let body = quote! {
+                match self {
+                    #(#arms),*
+                }
+            };
+            (body, item_enum.ident, item_enum.generics)
+        }
         Item::Struct(item_struct) => {
-            let mut i: usize = 0;
-            let field_name = item_struct
-                .fields
-                .iter()
-                .find_map(|field| {
-                    i += 1;
-                    if field
-                        .ty
-                        .to_token_stream()
-                        .to_string()
-                        .trim()
-                        .ends_with("Span")
-                        || field.ident.to_token_stream().to_string().trim() == "span"
-                    {
-                        if let Some(ident) = field.ident.as_ref() {
-                            Some(quote!(self.#ident))
-                        } else {
-                            let lit = syn::Index::from(i - 1);
-                            Some(quote!(self.#lit))
-                        }
-                    } else {
-                        None
-                    }
-                })
-                .ok_or_else(|| {
-                    Error::new(item_struct.span(), "expected a field of type `quoth::Span`")
-                })?
-                .clone();
-            (field_name, item_struct.ident, item_struct.generics)
+            let members = span_members(&item_struct.fields)?;
+            if members.is_empty() {
+                return Err(Error::new(
+                    item_struct.span(),
+                    "expected a field of type `quoth::Span`",
+                ));
+            }
+            let spans: Vec<_> = members.iter().map(|member| quote!(self.#member)).collect();
+            (join_spans(&spans), item_struct.ident, item_struct.generics)
         }
-        _ => return Err(Error::new(item.span(), "expected struct")),
+        _ => return Err(Error::new(item.span(), "expected struct or enum")),
     };
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
     let tokens = quote! {
         impl #impl_generics quoth::Spanned for #ident #ty_generics #where_clause {
             fn span(&self) -> quoth::Span {
-                #field_name.clone()
+                #body
             }
         }
     };
     Ok(tokens)
 }
+
+/// Returns the [`Member`] that can be used to access the specified field.
+fn field_member(field: &Field, index: usize) -> Member {
+    match &field.ident {
+        Some(ident) => Member::Named(ident.clone()),
+        None => Member::Unnamed(index.into()),
+    }
+}
+
+/// Finds the [`Member`]s whose `Span`s make up the span of a value with the specified fields.
+///
+/// This is the field marked with `#[quoth(primary)]` if there is one, otherwise every field of
+/// type `Span`, otherwise a field named `span` (see [`span_field_index`]).
+fn span_members(fields: &Fields) -> Result<Vec<Member>> {
+    let members = |indices: Vec<usize>| {
+        indices
+            .into_iter()
+            .map(|i| field_member(fields.iter().nth(i).unwrap(), i))
+            .collect()
+    };
+    let mut marked = false;
+    for field in fields {
+        marked |= FieldAttrs::from_field(field)?.span;
+    }
+    let typed: Vec<usize> = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| is_span_type(&field.ty))
+        .map(|(i, _)| i)
+        .collect();
+    if !marked && typed.len() > 1 {
+        return Ok(members(typed));
+    }
+    Ok(members(span_field_index(fields)?.into_iter().collect()))
+}
+
+/// Generates an expression joining the specified `Span` expressions, keeping the first one if
+/// a join fails.
+fn join_spans(spans: &[impl ToTokens]) -> TokenStream2 {
+    let (first, rest) = spans.split_first().unwrap();
+    quote! {{
+        let mut __span = quoth::Span::clone(&#first);
+        #(__span = __span.join(&#rest).unwrap_or(__span);)*
+        __span
+    }}
+}
+
+/// Finds the index of the field holding the `Span` within the specified fields.
+///
+/// A field explicitly marked with `#[quoth(span)]` always wins. Otherwise the field whose type
+/// is exactly `Span` (or a path ending in `::Span`, such as `quoth::Span`) is used, and
+/// finally a field named `span` is used as a fallback, which covers type aliases.
+fn span_field_index(fields: &Fields) -> Result<Option<usize>> {
+    let mut marked = None;
+    for (i, field) in fields.iter().enumerate() {
+        if FieldAttrs::from_field(field)?.span {
+            if marked.is_some() {
+                return Err(Error::new(
+                    field.span(),
+                    "only one field can be marked with `#[quoth(primary)]` or `#[quoth(span)]`",
+                ));
+            }
+            marked = Some(i);
+        }
+    }
+    if marked.is_some() {
+        return Ok(marked);
+    }
+    let mut typed = None;
+    for (i, field) in fields.iter().enumerate() {
+        if is_span_type(&field.ty) {
+            if typed.is_some() {
+                return Err(Error::new(
+                    field.span(),
+                    "multiple fields of type `quoth::Span` found, use `#[quoth(span)]` to \
+                    designate which one is the primary span",
+                ));
+            }
+            typed = Some(i);
+        }
+    }
+    if typed.is_some() {
+        return Ok(typed);
+    }
+    Ok(fields
+        .iter()
+        .position(|field| field.ident.as_ref().is_some_and(|ident| ident == "span")))
+}
+
+/// Returns `T` if the specified type is a `Box<T>` or an `Rc<T>`, which are parsed by parsing
+/// a `T` and wrapping it, since they can't implement `Parsable` themselves.
+fn pointee_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    if type_path.qself.is_some() {
+        return None;
+    }
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Box" && segment.ident != "Rc" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.iter().collect::<Vec<_>>()[..] {
+        [GenericArgument::Type(inner)] => Some(inner),
+        _ => None,
+    }
+}
+
+/// Returns whether the specified type is a path to `Span`, such as `Span` or `quoth::Span`.
+fn is_span_type(ty: &Type) -> bool {
+    is_type_named(ty, "Span")
+}
+
+/// Returns whether the specified type is a path to `Exact`, such as `Exact` or
+/// `quoth::parsable::Exact`.
+fn is_exact_type(ty: &Type) -> bool {
+    is_type_named(ty, "Exact")
+}
+
+/// Returns whether the specified type is a path ending in `name`, without generic arguments.
+fn is_type_named(ty: &Type, name: &str) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    if type_path.qself.is_some() {
+        return false;
+    }
+    type_path
+        .path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == name && segment.arguments.is_none())
+}
+
+/// Derives `Parsable` for the annotated struct by parsing each of its fields, in declaration
+/// order, using the field's own `Parsable` impl. The spans of the parsed fields are joined
+/// together and stored in the struct's `Span` field, which is not itself parsed. If any field
+/// fails to parse, the error is returned and the `ParseStream` is not advanced.
+///
+/// The following field attributes are supported:
+/// - `#[quoth(skip_ws)]` skips any whitespace before the field (and before its `exact`
+///   literal, if one is specified).
+/// - `#[quoth(exact = "=>")]` requires the specified literal to appear immediately before the
+///   field.
+///
+/// When applied to an enum, each variant is tried in declaration order (like a PEG ordered
+/// choice) and the first variant that parses successfully is returned. Each variant must
+/// either wrap a single `Parsable` field, or be annotated with `#[quoth(keyword = "if")]`, in
+/// which case the variant matches the specified keyword as a whole word, so `"iffy"` doesn't
+/// match `if`. A keyword variant is either a unit variant such as `If`, whose span (as derived
+/// by `Spanned`) is `Span::blank()`, or has a single field of type `Span` or `Exact`, such as
+/// `If(Span)`, which holds the span of the matched keyword. Prefer the latter if the variant
+/// is part of a larger syntax tree, so that the spans of its parents cover the keyword. If no
+/// variant matches, the resulting error lists all of the expected alternatives.
+///
+/// Fields (and variants) of type `Box<T>` or `Rc<T>` are parsed by parsing a `T` and wrapping
+/// it, which allows recursive grammars such as `enum Expr { Neg(Box<Neg>), Int(I64) }`. These
+/// smart pointers can't implement `Parsable` themselves, because `Parsable` requires `FromStr`,
+/// so this only works when the type is written as `Box<..>` or `Rc<..>`, not via an alias.
+/// Each derived `parse` counts as one level of nesting (see `ParseStream::enter`), so deeply
+/// nested input fails with an error once the stream's maximum depth is exceeded, rather than
+/// overflowing the stack.
+///
+/// Note that `Parsable` also requires `Display`, `FromStr` and `Spanned`, which can be
+/// derived via `ParsableExt` and `Spanned`.
+#[proc_macro_derive(Parsable, attributes(quoth))]
+pub fn derive_parsable(tokens: TokenStream) -> TokenStream {
+    match derive_parsable_internal(tokens.into()) {
+        Ok(tokens) => tokens,
+        Err(err) => err.to_compile_error(),
+    }
+    .into()
+}
+
+fn derive_parsable_internal(tokens: TokenStream2) -> Result<TokenStream2> {
+    let item = parse2::<Item>(tokens)?;
+    match item {
+        Item::Enum(item_enum) => derive_parsable_enum(item_enum),
+        Item::Struct(item_struct) => derive_parsable_struct(item_struct),
+        _ => Err(Error::new(item.span(), "expected struct or enum")),
+    }
+}
+
+/// The `#[quoth(..)]` attributes that can be attached to a field.
+#[derive(Default)]
+struct FieldAttrs {
+    span: bool,
+    skip_ws: bool,
+    exact: Option<LitStr>,
+}
+
+impl FieldAttrs {
+    fn from_field(field: &Field) -> Result<FieldAttrs> {
+        let mut attrs = FieldAttrs::default();
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("quoth"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("span") || meta.path.is_ident("primary") {
+                    attrs.span = true;
+                    Ok(())
+                } else if meta.path.is_ident("skip_ws") {
+                    attrs.skip_ws = true;
+                    Ok(())
+                } else if meta.path.is_ident("exact") {
+                    attrs.exact = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported quoth attribute"))
+                }
+            })?;
+        }
+        Ok(attrs)
+    }
+}
+
+fn derive_parsable_struct(item_struct: ItemStruct) -> Result<TokenStream2> {
+    let span_index = span_field_index(&item_struct.fields)?
+        .ok_or_else(|| Error::new(item_struct.span(), "expected a field of type `quoth::Span`"))?;
+    let mut steps = Vec::new();
+    let mut values = Vec::new();
+    for (i, field) in item_struct.fields.iter().enumerate() {
+        let var = format_ident!("__field_{}", i);
+        values.push(var.clone());
+        if i == span_index {
+            continue;
+        }
+        let attrs = FieldAttrs::from_field(field)?;
+        let skip_ws = attrs.skip_ws.then(|| {
+            quote! {
+                let _ = stream.parse::<quoth::parsable::Optional<quoth::parsable::Whitespace>>()?;
+            }
+        });
+        if let Some(exact) = &attrs.exact {
+            steps.push(quote! {
+                #skip_ws
+                let __exact = stream.parse_str(#exact)?;
+                __span = __span.join(&quoth::Spanned::span(&__exact)).unwrap_or(__span);
+            });
+        }
+        let ty = &field.ty;
+        let parse = match pointee_type(ty) {
+            Some(inner) => quote!(<#ty>::new(stream.parse::<#inner>()?)),
+            None => quote!(stream.parse::<#ty>()?),
+        };
+        steps.push(quote! {
+            #skip_ws
+            let #var = #parse;
+            __span = __span.join(&quoth::Spanned::span(&#var)).unwrap_or(__span);
+        });
+    }
+    let span_var = &values[span_index];
+    steps.push(quote! {
+        if __span.is_blank() {
+            __span = quoth::Span::new(stream.source().clone(), stream.position..stream.position);
+        }
+        let #span_var = __span;
+    });
+    let construct = match &item_struct.fields {
+        syn::Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote!(Self { #(#names: #values),* })
+        }
+        _ => quote!(Self(#(#values),*)),
+    };
+    let ident = &item_struct.ident;
+    let (impl_generics, ty_generics, where_clause) = item_struct.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics quoth::Parsable for #ident #ty_generics #where_clause {
+            fn parse(stream: &mut quoth::ParseStream) -> quoth::Result<Self> {
+                let _guard = stream.enter()?;
+                stream.transaction(|stream| {
+                    let mut __span = quoth::Span::blank();
+                    #(#steps)*
+                    Ok(#construct)
+                })
+            }
+        }
+    })
+}
+
+/// The `#[quoth(..)]` attributes that can be attached to an enum variant.
+#[derive(Default)]
+struct VariantAttrs {
+    keyword: Option<LitStr>,
+}
+
+impl VariantAttrs {
+    fn from_variant(variant: &Variant) -> Result<VariantAttrs> {
+        let mut attrs = VariantAttrs::default();
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("quoth"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("keyword") {
+                    attrs.keyword = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported quoth attribute"))
+                }
+            })?;
+        }
+        Ok(attrs)
+    }
+}
+
+fn derive_parsable_enum(item_enum: ItemEnum) -> Result<TokenStream2> {
+    let mut attempts = Vec::new();
+    let mut unparse_arms = Vec::new();
+    let mut expected = Vec::new();
+    for variant in &item_enum.variants {
+        let variant_ident = &variant.ident;
+        let attrs = VariantAttrs::from_variant(variant)?;
+        match (&variant.fields, attrs.keyword) {
+            (fields, Some(keyword)) if fields.len() == 1 => {
+                let field = fields.iter().next().unwrap();
+                let member = field_member(field, 0);
+                let value = if is_span_type(&field.ty) {
+                    quote!(quoth::Spanned::span(&exact))
+                } else if is_exact_type(&field.ty) {
+                    quote!(exact)
+                } else {
+                    return Err(Error::new(
+                        field.ty.span(),
+                        "keyword variants must have a single field of type `Span` or `Exact`",
+                    ));
+                };
+                expected.push(format!("`{}`", keyword.value()));
+                attempts.push(quote! {
+                    let mut fork = stream.fork();
+                    if let Ok(exact) = fork.parse_keyword(#keyword) {
+                        *stream = fork;
+                        return Ok(Self::#variant_ident { #member: #value });
+                    }
+                });
+                unparse_arms.push(quote!(Self::#variant_ident { .. } => f.write_str(#keyword)));
+            }
+            (Fields::Unit, Some(keyword)) => {
+                expected.push(format!("`{}`", keyword.value()));
+                attempts.push(quote! {
+                    let mut fork = stream.fork();
+                    if fork.parse_keyword(#keyword).is_ok() {
+                        *stream = fork;
+                        return Ok(Self::#variant_ident);
+                    }
+                });
+                unparse_arms.push(quote!(Self::#variant_ident => f.write_str(#keyword)));
+            }
+            (Fields::Unit, None) => {
+                return Err(Error::new(
+                    variant.span(),
+                    "unit variants must specify a `#[quoth(keyword = \"..\")]` to match",
+                ))
+            }
+            (fields, None) if fields.len() == 1 => {
+                let field = fields.iter().next().unwrap();
+                let member = field_member(field, 0);
+                let ty = &field.ty;
+                let pointee = pointee_type(ty);
+                let parsed_ty = pointee.unwrap_or(ty);
+                expected.push(format!(
+                    "`{}`",
+                    parsed_ty.to_token_stream().to_string().replace(' ', "")
+                ));
+                let (value, inner) = match pointee {
+                    Some(_) => (quote!(<#ty>::new(value)), quote!(&**inner)),
+                    None => (quote!(value), quote!(inner)),
+                };
+                attempts.push(quote! {
+                    let mut fork = stream.fork();
+                    if let Ok(value) = fork.parse::<#parsed_ty>() {
+                        *stream = fork;
+                        return Ok(Self::#variant_ident { #member: #value });
+                    }
+                });
+                unparse_arms.push(quote! {
+                    Self::#variant_ident { #member: inner } => quoth::Parsable::unparse(#inner, f)
+                });
+            }
+            (_, Some(keyword)) => {
+                return Err(Error::new(
+                    keyword.span(),
+                    "keyword variants must have a single field of type `Span` or `Exact`",
+                ))
+            }
+            _ => {
+                return Err(Error::new(
+                    variant.span(),
+                    "expected a unit variant or a variant with a single field",
+                ))
+            }
+        }
+    }
+    let message = format!("expected one of {}", expected.join(", "));
+    let ident = &item_enum.ident;
+    let (impl_generics, ty_generics, where_clause) = item_enum.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics quoth::Parsable for #ident #ty_generics #where_clause {
+            fn parse(stream: &mut quoth::ParseStream) -> quoth::Result<Self> {
+                let _guard = stream.enter()?;
+                #(#attempts)*
+                Err(quoth::Error::new(stream.current_span(), #message))
+            }
+
+            fn unparse(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                match self {
+                    #(#unparse_arms),*
+                }
+            }
+        }
+    })
+}
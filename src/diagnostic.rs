@@ -1,10 +1,19 @@
-use std::fmt::Display;
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Write},
+    ops::Range,
+};
 
 use crate as quoth;
 
 use super::*;
 
 /// Represents the severity of a [`Diagnostic`].
+///
+/// Levels are ordered by severity, from least to most severe: [`Help`](DiagnosticLevel::Help)
+/// `<` [`Note`](DiagnosticLevel::Note) `<` [`Warning`](DiagnosticLevel::Warning) `<`
+/// [`Error`](DiagnosticLevel::Error), so `level >= DiagnosticLevel::Warning` selects warnings
+/// and errors.
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
 pub enum DiagnosticLevel {
     /// Represents an error diagnostic.
@@ -28,6 +37,30 @@ impl Display for DiagnosticLevel {
     }
 }
 
+impl DiagnosticLevel {
+    /// Returns the rank of this level by severity, with the least severe level first.
+    fn severity(self) -> u8 {
+        match self {
+            DiagnosticLevel::Help => 0,
+            DiagnosticLevel::Note => 1,
+            DiagnosticLevel::Warning => 2,
+            DiagnosticLevel::Error => 3,
+        }
+    }
+}
+
+impl Ord for DiagnosticLevel {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.severity().cmp(&other.severity())
+    }
+}
+
+impl PartialOrd for DiagnosticLevel {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 /// Represents a diagnostic message that can be displayed to the user, typically indicating a
 /// parsing error or highlighting some fact about a [`Span`] of input
 ///
@@ -35,6 +68,10 @@ impl Display for DiagnosticLevel {
 /// parent [`Diagnostic`]. For example, a [`Diagnostic`] about a missing semicolon might have a
 /// child [`Diagnostic`] about a missing closing brace.
 ///
+/// In addition to its primary [`Span`], a [`Diagnostic`] can carry secondary labeled spans
+/// (see [`Diagnostic::with_label`]), which are underlined with `-` alongside the primary `^`
+/// carets whenever they fall on the same lines, similar to rustc's secondary labels.
+///
 /// Note that the [`Display`] implementation for [`Diagnostic`] is designed to be
 /// human-readable, and is how [`Diagnostic`]s are intended to be displayed to the user.
 ///
@@ -61,6 +98,8 @@ pub struct Diagnostic {
     span: Span,
     message: String,
     context_name: Option<String>,
+    label: Option<String>,
+    labels: Vec<(Span, Option<String>)>,
     children: Vec<Diagnostic>,
 }
 
@@ -81,10 +120,94 @@ impl Diagnostic {
             span,
             message: message.to_string(),
             context_name: context_name.map(|n| n.to_string()),
+            label: None,
+            labels: Vec::new(),
             children,
         }
     }
 
+    /// Creates a new [`DiagnosticLevel::Error`] [`Diagnostic`] with no children.
+    pub fn error(span: Span, message: impl ToString) -> Diagnostic {
+        Diagnostic::new(
+            DiagnosticLevel::Error,
+            span,
+            message,
+            Option::<String>::None,
+            Vec::new(),
+        )
+    }
+
+    /// Creates a new [`DiagnosticLevel::Warning`] [`Diagnostic`] with no children.
+    pub fn warning(span: Span, message: impl ToString) -> Diagnostic {
+        Diagnostic::new(
+            DiagnosticLevel::Warning,
+            span,
+            message,
+            Option::<String>::None,
+            Vec::new(),
+        )
+    }
+
+    /// Appends the given child [`Diagnostic`] to this [`Diagnostic`].
+    pub fn with_child(mut self, child: Diagnostic) -> Diagnostic {
+        self.children.push(child);
+        self
+    }
+
+    /// Appends a [`DiagnosticLevel::Note`] child with the given span and message to this
+    /// [`Diagnostic`], e.g. to point out where something was originally defined.
+    pub fn with_note(self, span: Span, message: impl ToString) -> Diagnostic {
+        self.with_child(Diagnostic::new(
+            DiagnosticLevel::Note,
+            span,
+            message,
+            Option::<String>::None,
+            Vec::new(),
+        ))
+    }
+
+    /// Appends a [`DiagnosticLevel::Help`] child with the given span and message to this
+    /// [`Diagnostic`].
+    pub fn with_help(self, span: Span, message: impl ToString) -> Diagnostic {
+        self.with_child(Diagnostic::new(
+            DiagnosticLevel::Help,
+            span,
+            message,
+            Option::<String>::None,
+            Vec::new(),
+        ))
+    }
+
+    /// Sets the label printed to the right of the `^` underline of the primary span of this
+    /// [`Diagnostic`], e.g. `unexpected token here`.
+    ///
+    /// The label belongs to the [`Diagnostic`] rather than to its [`Span`], and is rendered the
+    /// same way as the labels of secondary spans (see [`Diagnostic::with_label`]).
+    pub fn with_primary_label(mut self, label: impl ToString) -> Diagnostic {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// Attaches a secondary [`Span`] with the given label to this [`Diagnostic`], e.g. to point
+    /// out the other half of a conflict.
+    ///
+    /// Secondary spans in the same source as the primary span are underlined with `-` in the
+    /// same snippet, with the label printed beneath (or beside) the underline. Where a secondary
+    /// span overlaps the primary span, the primary `^` carets take precedence.
+    pub fn with_label(mut self, span: Span, label: impl ToString) -> Diagnostic {
+        self.labels.push((span, Some(label.to_string())));
+        self
+    }
+
+    /// Attaches the given secondary [`Span`]s to this [`Diagnostic`] without labels.
+    ///
+    /// See [`Diagnostic::with_label`] for how secondary spans are rendered.
+    pub fn with_spans(mut self, spans: impl MultiSpan) -> Diagnostic {
+        self.labels
+            .extend(spans.into_spans().into_iter().map(|span| (span, None)));
+        self
+    }
+
     /// Sets the level of this [`Diagnostic`] to the given level.
     pub fn set_level(&mut self, level: DiagnosticLevel) {
         self.level = level;
@@ -105,6 +228,15 @@ impl Diagnostic {
         self.level
     }
 
+    /// Returns the most severe [`DiagnosticLevel`] of this [`Diagnostic`] and all of its
+    /// children, recursively.
+    pub fn max_level(&self) -> DiagnosticLevel {
+        self.children
+            .iter()
+            .map(Diagnostic::max_level)
+            .fold(self.level, Ord::max)
+    }
+
     /// Returns the string message of this [`Diagnostic`].
     pub fn message(&self) -> &str {
         &self.message
@@ -113,14 +245,31 @@ impl Diagnostic {
     /// Returns the name of the context that this [`Diagnostic`] is associated with.
     ///
     /// This is typically the name of the input that the [`Diagnostic`] is associated with, but
-    /// can be overridden by setting the context name.
+    /// can be overridden by setting the context name. If no context name is set, the
+    /// [`Source::name`] of the [`Diagnostic`]'s span is used, falling back to "input".
     pub fn context_name(&self) -> &str {
-        match &self.context_name {
-            Some(context_name) => context_name,
-            None => "input",
+        self.context_name_for(&self.span)
+    }
+
+    /// Returns the context name to display for the given span of this [`Diagnostic`].
+    fn context_name_for<'a>(&'a self, span: &'a Span) -> &'a str {
+        match (&self.context_name, span.source().name()) {
+            (Some(context_name), _) => context_name,
+            (None, Some(name)) => name,
+            (None, None) => "input",
         }
     }
 
+    /// Returns the label of the primary span of this [`Diagnostic`], if any.
+    pub fn primary_label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns the secondary [`Span`]s of this [`Diagnostic`], along with their labels.
+    pub fn labels(&self) -> &[(Span, Option<String>)] {
+        &self.labels
+    }
+
     /// Returns a [`Vec`] of the children of this [`Diagnostic`].
     pub fn children(&self) -> &Vec<Diagnostic> {
         &self.children
@@ -129,80 +278,738 @@ impl Diagnostic {
     /// Returns a [`Span`] that represents the range of the input that this [`Diagnostic`] is
     /// associated with.
     ///
-    /// Identical to calling `self.span()` when the [`Diagnostic`] has no children.
-    pub fn merged_span(&self) -> core::result::Result<Span, SpanJoinError> {
-        let mut merged_span = self.span.clone();
-        for child in &self.children {
-            merged_span = merged_span.join(&child.merged_span()?)?;
+    /// Identical to calling `self.span()` when the [`Diagnostic`] has no children. Children
+    /// whose spans come from a different [`Source`] than this [`Diagnostic`] (e.g. an included
+    /// sub-source) are skipped rather than failing the merge; use [`Diagnostic::merged_spans`]
+    /// to get those as well.
+    pub fn merged_span(&self) -> Span {
+        self.merged_spans().swap_remove(0)
+    }
+
+    /// Returns the start and end [`LineCol`] of the [`merged_span`](Diagnostic::merged_span)
+    /// of this [`Diagnostic`], i.e. the full extent of it and its children.
+    pub fn line_col_range(&self) -> (LineCol, LineCol) {
+        let span = self.merged_span();
+        (span.start(), span.end())
+    }
+
+    /// Returns the merged [`Span`]s of this [`Diagnostic`] and its children, with one [`Span`]
+    /// per distinct [`Source`].
+    ///
+    /// The first [`Span`] always covers this [`Diagnostic`]'s own [`Source`], with the
+    /// remaining sources following in the order in which they are first encountered.
+    pub fn merged_spans(&self) -> Vec<Span> {
+        let mut merged = vec![self.span.clone()];
+        for span in self.children.iter().flat_map(Diagnostic::merged_spans) {
+            let joined = merged
+                .iter()
+                .enumerate()
+                .find_map(|(i, existing)| Some((i, existing.join(&span).ok()?)));
+            match joined {
+                Some((i, joined)) => merged[i] = joined,
+                None => merged.push(span),
+            }
         }
-        Ok(merged_span)
+        merged
     }
 }
 
 impl Display for Diagnostic {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.render(f, Style::default())
+    }
+}
+
+/// A collection of [`Diagnostic`]s accumulated while parsing, allowing several problems to be
+/// reported at once instead of stopping at the first one.
+///
+/// Diagnostics are kept in the order in which they were pushed. Use
+/// [`DiagnosticBag::into_sorted`] to order them by [`Span`] for reporting.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub struct DiagnosticBag {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagnosticBag {
+    /// Creates a new, empty [`DiagnosticBag`].
+    pub fn new() -> DiagnosticBag {
+        DiagnosticBag::default()
+    }
+
+    /// Adds the specified [`Diagnostic`] to this [`DiagnosticBag`].
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Returns the number of [`Diagnostic`]s in this [`DiagnosticBag`].
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    /// Returns `true` if this [`DiagnosticBag`] contains no [`Diagnostic`]s.
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Returns `true` if any of the [`Diagnostic`]s in this [`DiagnosticBag`] is an
+    /// [`DiagnosticLevel::Error`].
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.level() == DiagnosticLevel::Error)
+    }
+
+    /// Returns an iterator over the [`Diagnostic`]s in this [`DiagnosticBag`] in the order in
+    /// which they were pushed.
+    pub fn iter(&self) -> core::slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    /// Consumes this [`DiagnosticBag`], returning its [`Diagnostic`]s in the order in which
+    /// they were pushed.
+    pub fn into_vec(self) -> Vec<Diagnostic> {
+        self.diagnostics
+    }
+
+    /// Consumes this [`DiagnosticBag`], returning its [`Diagnostic`]s ordered by [`Span`].
+    ///
+    /// The sort is stable, so diagnostics with identical spans keep the order in which they
+    /// were pushed.
+    pub fn into_sorted(mut self) -> Vec<Diagnostic> {
+        self.diagnostics.sort_by_key(|diagnostic| diagnostic.span());
+        self.diagnostics
+    }
+}
+
+impl From<Vec<Diagnostic>> for DiagnosticBag {
+    fn from(diagnostics: Vec<Diagnostic>) -> Self {
+        DiagnosticBag { diagnostics }
+    }
+}
+
+impl Extend<Diagnostic> for DiagnosticBag {
+    fn extend<T: IntoIterator<Item = Diagnostic>>(&mut self, iter: T) {
+        self.diagnostics.extend(iter);
+    }
+}
+
+impl FromIterator<Diagnostic> for DiagnosticBag {
+    fn from_iter<T: IntoIterator<Item = Diagnostic>>(iter: T) -> Self {
+        DiagnosticBag {
+            diagnostics: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl IntoIterator for DiagnosticBag {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a DiagnosticBag {
+    type Item = &'a Diagnostic;
+    type IntoIter = core::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.iter()
+    }
+}
+
+impl DiagnosticLevel {
+    /// Returns the ANSI escape sequence used to color this [`DiagnosticLevel`].
+    fn ansi_color(&self) -> &'static str {
+        match self {
+            DiagnosticLevel::Error => "\x1b[1;31m",
+            DiagnosticLevel::Warning => "\x1b[1;33m",
+            DiagnosticLevel::Note => "\x1b[1;32m",
+            DiagnosticLevel::Help => "\x1b[1;36m",
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DiagnosticLevel {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Diagnostic {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("Diagnostic", 6)?;
+        state.serialize_field("level", &self.level)?;
+        state.serialize_field("message", &self.message)?;
+        state.serialize_field("location", &self.span.location(self.context_name()))?;
+        match &self.label {
+            Some(label) => state.serialize_field("label", label)?,
+            None => state.skip_field("label")?,
+        }
+        let labels: Vec<_> = self
+            .labels
+            .iter()
+            .map(|(span, label)| SerializedLabel {
+                label: label.as_deref(),
+                location: span.location(self.context_name_for(span)),
+            })
+            .collect();
+        state.serialize_field("labels", &labels)?;
+        state.serialize_field("children", &self.children)?;
+        state.end()
+    }
+}
+
+/// The serialized form of a secondary labeled [`Span`] of a [`Diagnostic`].
+#[cfg(feature = "serde")]
+struct SerializedLabel<'a> {
+    label: Option<&'a str>,
+    location: SpanLocation,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SerializedLabel<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("Label", 2)?;
+        state.serialize_field("label", &self.label)?;
+        state.serialize_field("location", &self.location)?;
+        state.end()
+    }
+}
+
+const ANSI_DIM: &str = "\x1b[2m";
+const ANSI_SECONDARY: &str = "\x1b[1;34m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// The tab width used when rendering a [`Diagnostic`] via its [`Display`] implementation.
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// The maximum number of columns of a source line shown when rendering a [`Diagnostic`] via
+/// its [`Display`] implementation.
+pub const DEFAULT_MAX_LINE_WIDTH: usize = 120;
+
+/// Options that control how a [`Diagnostic`] is rendered by [`Diagnostic::write_with`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct RenderOptions {
+    /// The number of columns between tab stops. Tabs in the source text are expanded to
+    /// spaces accordingly, both in the displayed source lines and when positioning carets, so
+    /// that `\t`-indented code is underlined correctly. Defaults to [`DEFAULT_TAB_WIDTH`].
+    pub tab_width: usize,
+    /// The maximum number of columns of each source line to display, or `None` to always
+    /// display whole lines. Defaults to [`DEFAULT_MAX_LINE_WIDTH`].
+    ///
+    /// Longer lines (e.g. minified or generated code) are cut down to a window of this many
+    /// columns centered on the underlined text, with `...` marking each truncated side.
+    pub max_line_width: Option<usize>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            tab_width: DEFAULT_TAB_WIDTH,
+            max_line_width: Some(DEFAULT_MAX_LINE_WIDTH),
+        }
+    }
+}
+
+/// Internal state shared by the different parts of the [`Diagnostic`] renderer.
+#[derive(Copy, Clone, Default)]
+struct Style {
+    options: RenderOptions,
+    use_color: bool,
+}
+
+impl Style {
+    fn dim(&self) -> Option<&'static str> {
+        self.use_color.then_some(ANSI_DIM)
+    }
+
+    fn level(&self, level: DiagnosticLevel) -> Option<&'static str> {
+        self.use_color.then(|| level.ansi_color())
+    }
+
+    fn secondary(&self) -> Option<&'static str> {
+        self.use_color.then_some(ANSI_SECONDARY)
+    }
+}
+
+/// Writes `text` wrapped in the specified ANSI `color`, or as-is if `color` is `None`.
+fn paint(f: &mut impl Write, color: Option<&str>, text: impl Display) -> std::fmt::Result {
+    match color {
+        Some(color) => write!(f, "{color}{text}{ANSI_RESET}"),
+        None => write!(f, "{text}"),
+    }
+}
+
+impl Diagnostic {
+    /// Writes this [`Diagnostic`] to `f`, coloring the output with ANSI escape sequences if
+    /// `use_color` is `true`.
+    ///
+    /// The level and caret underline are colored by severity and line numbers are dimmed. When
+    /// `use_color` is `false`, the output is identical to the [`Display`] implementation, which
+    /// is useful for callers that need to disable color when not writing to a TTY.
+    #[cfg(feature = "color")]
+    pub fn write_colored(&self, f: &mut impl Write, use_color: bool) -> std::fmt::Result {
+        let style = Style {
+            use_color,
+            ..Style::default()
+        };
+        self.render(f, style)
+    }
+
+    /// Writes this [`Diagnostic`] to `f` using the specified [`RenderOptions`].
+    ///
+    /// With [`RenderOptions::default()`] the output is identical to the [`Display`]
+    /// implementation.
+    pub fn write_with(&self, f: &mut impl Write, options: RenderOptions) -> std::fmt::Result {
+        let style = Style {
+            options,
+            ..Style::default()
+        };
+        self.render(f, style)
+    }
+
+    /// Returns a one-line summary of this [`Diagnostic`] without the source snippet, such as
+    /// `error at input:3:5: expected digit`, which is handy for logs and panic messages.
+    ///
+    /// The location is written the same way as in the full rendering, with tabs expanded to
+    /// [`DEFAULT_TAB_WIDTH`] columns. Children and labels are omitted.
+    pub fn short(&self) -> String {
+        let LineCol { line, col } = self.span.visual_start(DEFAULT_TAB_WIDTH);
+        let context = match self.span.source_path() {
+            Some(path) => path.display().to_string(),
+            None => self.context_name().to_string(),
+        };
+        format!(
+            "{} at {context}:{}:{col}: {}",
+            self.level,
+            line + 1,
+            self.message
+        )
+    }
+
+    /// Serializes this [`Diagnostic`] (and its children) as JSON.
+    ///
+    /// The schema is stable and looks like the following, where `source` is the path of the
+    /// source file, or the context name if the source was not read from a file:
+    ///
+    /// ```json
+    /// {
+    ///   "level": "error",
+    ///   "message": "expected digit",
+    ///   "location": {
+    ///     "source": "input",
+    ///     "byte_range": { "start": 5, "end": 7 },
+    ///     "char_range": { "start": 5, "end": 7 },
+    ///     "start": { "line": 0, "col": 5 },
+    ///     "end": { "line": 0, "col": 7 }
+    ///   },
+    ///   "labels": [],
+    ///   "children": []
+    /// }
+    /// ```
+    ///
+    /// `byte_range` holds UTF-8 byte offsets into the source text, whereas `char_range` and the
+    /// columns of `start` and `end` count characters, see [`SpanLocation`].
+    ///
+    /// If the primary span has a label (see [`Diagnostic::with_primary_label`]), it is included
+    /// as a `label` string following `location`.
+    ///
+    /// Each entry of `labels` is an object with a `label` (a string, or `null` for unlabeled
+    /// spans) and a `location` of the same shape as above.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("diagnostics are always serializable")
+    }
+
+    fn render(&self, f: &mut impl Write, style: Style) -> std::fmt::Result {
         let level = self.level;
         let message = &self.message;
-        write!(f, "{level}: {message}\n")?;
+        paint(f, style.level(level), level)?;
+        writeln!(f, ": {message}")?;
         let span = self.span();
-        let LineCol { line, col } = span.start();
-        let num_width = if line == 0 {
-            1
-        } else {
-            (line as f64).log10() as usize + 1
-        };
+        let (marks, foreign): (Vec<_>, Vec<_>) = self
+            .marks()
+            .into_iter()
+            .partition(|mark| mark.span.same_source(&span));
+        let num_width = gutter_width(&marks);
+        self.render_location(f, &span, num_width, style)?;
+        for _ in 0..num_width {
+            write!(f, " ")?;
+        }
+        paint(f, style.dim(), " |")?;
+        writeln!(f)?;
+        render_snippet(f, &marks, num_width, level, style)?;
+        for mark in foreign {
+            let marks = [mark];
+            let num_width = gutter_width(&marks);
+            self.render_location(f, marks[0].span, num_width, style)?;
+            render_snippet(f, &marks, num_width, level, style)?;
+        }
+        for child in &self.children {
+            child.render_child(f, &span, num_width, style)?;
+        }
+        Ok(())
+    }
+
+    /// Renders this [`Diagnostic`] as a child of a [`Diagnostic`] with the given span and
+    /// gutter width.
+    ///
+    /// Error and warning children are rendered in full, whereas note and help children are
+    /// rendered as an indented `= note: ..` line, followed by their own location and source
+    /// snippet only if they point somewhere other than the line of the parent span.
+    fn render_child(
+        &self,
+        f: &mut impl Write,
+        parent: &Span,
+        parent_width: usize,
+        style: Style,
+    ) -> std::fmt::Result {
+        if matches!(
+            self.level,
+            DiagnosticLevel::Error | DiagnosticLevel::Warning
+        ) {
+            return self.render(f, style);
+        }
+        let level = self.level;
+        let message = &self.message;
+        for _ in 0..=parent_width {
+            write!(f, " ")?;
+        }
+        paint(f, style.dim(), "= ")?;
+        paint(f, style.level(level), level)?;
+        writeln!(f, ": {message}")?;
+        let span = self.span();
+        let marks = self.marks();
+        let num_width = gutter_width(&marks);
+        if !span.same_source(parent) || span.start().line != parent.start().line {
+            self.render_location(f, &span, num_width, style)?;
+            render_snippet(f, &marks, num_width, level, style)?;
+        }
+        for child in &self.children {
+            child.render_child(f, &span, num_width, style)?;
+        }
+        Ok(())
+    }
+
+    /// Renders the ` --> path:line:col` line pointing at the start of the given span.
+    fn render_location(
+        &self,
+        f: &mut impl Write,
+        span: &Span,
+        num_width: usize,
+        style: Style,
+    ) -> std::fmt::Result {
+        let LineCol { line, col } = span.visual_start(style.options.tab_width);
         for _ in 1..num_width {
             write!(f, " ")?;
         }
-        write!(f, " --> ")?;
+        paint(f, style.dim(), " --> ")?;
         match span.source_path() {
             Some(path) => write!(f, "{}", path.display())?,
-            None => write!(f, "{}", self.context_name())?,
+            None => write!(f, "{}", self.context_name_for(span))?,
         }
         let real_line = line + 1;
-        write!(f, ":{real_line}:{col}\n")?;
-        for _ in 0..num_width {
-            write!(f, " ")?;
+        writeln!(f, ":{real_line}:{col}")
+    }
+
+    /// Returns the [`Mark`]s to underline for this [`Diagnostic`], with the primary span last
+    /// so that it is drawn over any overlapping secondary spans.
+    fn marks(&self) -> Vec<Mark<'_>> {
+        let mut marks: Vec<_> = self
+            .labels
+            .iter()
+            .map(|(span, label)| Mark {
+                span,
+                primary: false,
+                label: label.as_deref(),
+            })
+            .collect();
+        marks.push(Mark {
+            span: &self.span,
+            primary: true,
+            label: self.label.as_deref(),
+        });
+        marks
+    }
+}
+
+/// A [`Span`] to be underlined within a source snippet, optionally with a label.
+struct Mark<'a> {
+    span: &'a Span,
+    primary: bool,
+    label: Option<&'a str>,
+}
+
+/// A single rendered cell of an underline, along with the index of the [`Mark`] it belongs to.
+type Cell = (char, Option<usize>);
+
+/// A displayed source line: its index, its text, and the ranges of each [`Mark`] on it.
+type SnippetLine<'a> = (usize, IndexedSlice<'a>, Vec<(usize, Range<usize>)>);
+
+/// Returns the width of the line number gutter for a snippet covering the given marks.
+fn gutter_width(marks: &[Mark]) -> usize {
+    let last = marks.iter().map(|mark| mark.span.end().line).max();
+    (last.unwrap_or(0) + 1).to_string().len()
+}
+
+/// Renders the source lines covered by the given marks, with an underline beneath each.
+///
+/// The primary mark is underlined with `^` and secondary marks with `-`. Labels are printed
+/// to the right of the underline when nothing else is underlined after them, and otherwise on
+/// the following lines, connected to their spans with `|`. Lines between the marked lines are
+/// elided with `...`.
+///
+/// Tabs are expanded to spaces so that the underlines line up with the displayed source text.
+fn render_snippet(
+    f: &mut impl Write,
+    marks: &[Mark],
+    num_width: usize,
+    level: DiagnosticLevel,
+    style: Style,
+) -> std::fmt::Result {
+    let tab_width = style.options.tab_width;
+    let mut lines: Vec<SnippetLine> = Vec::new();
+    let mut last_lines = vec![0; marks.len()];
+    for (m, mark) in marks.iter().enumerate() {
+        for (line, text, range) in mark.span.source_line_numbers() {
+            last_lines[m] = line;
+            match lines.iter_mut().find(|(l, _, _)| *l == line) {
+                Some((_, _, ranges)) => ranges.push((m, range)),
+                None => lines.push((line, text, vec![(m, range)])),
+            }
+        }
+    }
+    lines.sort_by_key(|(line, _, _)| *line);
+    let mut prev_line = None;
+    for (line, lin, ranges) in lines {
+        if prev_line.is_some_and(|prev| line > prev + 1) {
+            paint(f, style.dim(), "...")?;
+            writeln!(f)?;
         }
-        write!(f, " |\n")?;
-        for (i, (lin, range)) in span.source_lines().into_iter().enumerate() {
-            let num = i + line + 1;
-            write!(f, "{num} | {lin}\n")?;
-            for _ in 0..num_width {
-                write!(f, " ")?;
+        prev_line = Some(line);
+        let num = line + 1;
+        let chars = lin.chars();
+        // offsets[i] is the display column at which chars[i] starts
+        let mut offsets = Vec::with_capacity(chars.len() + 1);
+        // columns[i] is the text displayed at column i, i.e. a character along with any
+        // zero-width characters following it, or `None` for the second column of a wide
+        // character
+        let mut columns: Vec<Option<String>> = Vec::new();
+        // zero-width characters at the start of the line, shown along with the next column
+        let mut pending = String::new();
+        let mut col = 0;
+        for c in chars {
+            offsets.push(col);
+            let width = display_width(*c, col, tab_width);
+            col += width;
+            match (c, width) {
+                ('\t', _) => columns.extend(std::iter::repeat_n(Some(" ".into()), width)),
+                (c, 0) => match columns.iter_mut().rev().find_map(Option::as_mut) {
+                    Some(text) => text.push(*c),
+                    None => pending.push(*c),
+                },
+                (c, _) => {
+                    let mut text = std::mem::take(&mut pending);
+                    text.push(*c);
+                    columns.push(Some(text));
+                    columns.extend(std::iter::repeat_n(None, width - 1));
+                }
             }
-            write!(f, "   ")?;
-            for _ in 0..range.start {
-                write!(f, " ")?;
+        }
+        offsets.push(col);
+        let col_at = |i: usize| match offsets.get(i) {
+            Some(col) => *col,
+            None => col + i - chars.len(),
+        };
+        let mut cells: Vec<Cell> = Vec::new();
+        for (m, range) in &ranges {
+            let marker = if marks[*m].primary { '^' } else { '-' };
+            let end = col_at(range.end);
+            if cells.len() < end {
+                cells.resize(end, (' ', None));
             }
-            let chars = lin.chars();
             let mut prev = false;
-            for i in range {
-                let Some(char) = chars.get(i) else {
-                    write!(f, " ")?;
-                    prev = true;
-                    continue;
+            for i in range.clone() {
+                let mark = match chars.get(i) {
+                    Some(char) => {
+                        let current = char.is_whitespace();
+                        let next = chars.get(i + 1).is_some_and(|c| c.is_whitespace());
+                        let mark = if current && (next || prev) {
+                            ' '
+                        } else {
+                            marker
+                        };
+                        prev = current;
+                        mark
+                    }
+                    None => {
+                        prev = true;
+                        ' '
+                    }
                 };
-                let current = char.is_whitespace();
-                let next = if i + 1 < chars.len() {
-                    chars[i + 1].is_whitespace()
-                } else {
-                    false
-                };
-                if current && (next || prev) {
-                    write!(f, " ")?;
-                } else {
-                    write!(f, "^")?;
+                for cell in &mut cells[col_at(i)..col_at(i + 1)] {
+                    // blanks never erase another mark's underline, except for the primary mark
+                    if mark != ' ' || cell.0 == ' ' || marks[*m].primary {
+                        *cell = (mark, Some(*m));
+                    }
                 }
-                prev = current;
             }
-            write!(f, "\n")?;
         }
-        for child in &self.children {
-            write!(f, "{child}")?;
+        let (start, end) = line_window(&cells, col, style.options.max_line_width);
+        let ellipsis = |truncated: bool| if truncated { "..." } else { "" };
+        let (prefix, suffix) = (ellipsis(start > 0), ellipsis(end < col));
+        let text: String = (start..end)
+            .filter_map(|i| match &columns[i] {
+                // wide characters cut in half by the window are shown as a space
+                Some(_) if i + 1 == end && columns.get(i + 1) == Some(&None) => Some(" "),
+                None if i == start => Some(" "),
+                text => text.as_deref(),
+            })
+            .chain(columns.is_empty().then_some(pending.as_str()))
+            .collect();
+        paint(f, style.dim(), format_args!("{num:<num_width$} |"))?;
+        write!(f, " ")?;
+        if !prefix.is_empty() {
+            paint(f, style.dim(), prefix)?;
+        }
+        write!(f, "{text}")?;
+        if !suffix.is_empty() {
+            paint(f, style.dim(), suffix)?;
+        }
+        writeln!(f)?;
+        let shift = |col: usize| col.saturating_sub(start) + prefix.len();
+        let cells: Vec<Cell> = std::iter::repeat_n((' ', None), prefix.len())
+            .chain(cells.into_iter().skip(start))
+            // keep underlines past the end of the line (e.g. of a newline) unless truncated
+            .take(if suffix.is_empty() {
+                usize::MAX
+            } else {
+                end - start + prefix.len()
+            })
+            .collect();
+        let mut labels: Vec<(usize, usize, &str)> = ranges
+            .iter()
+            .filter(|(m, _)| last_lines[*m] == line)
+            .filter_map(|(m, range)| {
+                // anchor the label to the first visible cell of its underline
+                let col = cells
+                    .iter()
+                    .position(|(_, owner)| *owner == Some(*m))
+                    .unwrap_or(shift(col_at(range.start)));
+                Some((col, *m, marks[*m].label?))
+            })
+            .collect();
+        labels.sort_by_key(|(col, _, _)| *col);
+        let inline = labels.last().copied().filter(|(col, m, _)| {
+            cells
+                .iter()
+                .skip(*col)
+                .all(|(_, owner)| owner.is_none() || *owner == Some(*m))
+        });
+        if inline.is_some() {
+            labels.pop();
+        }
+        write!(f, "{:width$}", "", width = num_width + 3)?;
+        write_cells(f, &cells, marks, level, style)?;
+        if let Some((_, m, label)) = inline {
+            write!(f, " ")?;
+            write_cells(f, &label_cells(label, m), marks, level, style)?;
+        }
+        writeln!(f)?;
+        if labels.is_empty() {
+            continue;
+        }
+        let mut connectors: Vec<Cell> = Vec::new();
+        for (col, m, _) in &labels {
+            if connectors.len() <= *col {
+                connectors.resize(col + 1, (' ', None));
+            }
+            connectors[*col] = ('|', Some(*m));
+        }
+        write!(f, "{:width$}", "", width = num_width + 3)?;
+        write_cells(f, &connectors, marks, level, style)?;
+        writeln!(f)?;
+        for (j, (col, m, label)) in labels.iter().enumerate().rev() {
+            let mut row: Vec<Cell> = vec![(' ', None); *col];
+            for (left, m, _) in &labels[..j] {
+                if left < col {
+                    row[*left] = ('|', Some(*m));
+                }
+            }
+            row.extend(label_cells(label, *m));
+            write!(f, "{:width$}", "", width = num_width + 3)?;
+            write_cells(f, &row, marks, level, style)?;
+            writeln!(f)?;
         }
-        Ok(())
     }
+    Ok(())
+}
+
+/// Returns the range of display columns of a source line `width` columns wide to show, given
+/// the underline `cells` of the line and the maximum number of columns to show.
+///
+/// The window is centered on the underlined cells, or starts at the first of them if they
+/// don't all fit.
+fn line_window(cells: &[Cell], width: usize, max_width: Option<usize>) -> (usize, usize) {
+    let Some(max_width) = max_width.filter(|max_width| width > *max_width) else {
+        return (0, width);
+    };
+    let marked = |(_, (_, owner)): &(usize, &Cell)| owner.is_some();
+    let first = cells.iter().enumerate().find(marked).map_or(0, |(i, _)| i);
+    let last = cells
+        .iter()
+        .enumerate()
+        .rfind(marked)
+        .map_or(0, |(i, _)| i + 1);
+    let start = if last - first >= max_width {
+        first
+    } else {
+        ((first + last) / 2)
+            .saturating_sub(max_width / 2)
+            .min(width - max_width)
+    };
+    let start = start.min(width);
+    (start, (start + max_width).min(width))
+}
+
+/// Returns the cells of the given label, belonging to the [`Mark`] with index `m`.
+fn label_cells(label: &str, m: usize) -> Vec<Cell> {
+    label.chars().map(|c| (c, Some(m))).collect()
+}
+
+/// Writes the given cells, coloring each run of cells according to the [`Mark`] it belongs to.
+fn write_cells(
+    f: &mut impl Write,
+    cells: &[Cell],
+    marks: &[Mark],
+    level: DiagnosticLevel,
+    style: Style,
+) -> std::fmt::Result {
+    for run in cells.chunk_by(|a, b| a.1 == b.1) {
+        let text: String = run.iter().map(|(c, _)| c).collect();
+        let color = match run[0].1 {
+            Some(m) if marks[m].primary => style.level(level),
+            Some(_) => style.secondary(),
+            None => None,
+        };
+        paint(f, color, text)?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -215,9 +1022,11 @@ fn test_diagnostic_display_single_line() {
         message: "this is an error".to_string(),
         span: Span::new(Rc::new(Source::from_str("this is a triumph")), 5..7),
         context_name: Some("the thing".to_string()),
+        label: None,
+        labels: Vec::new(),
         children: Vec::new(),
     };
-    println!("{}", diag.to_string());
+    println!("{diag}");
     assert_eq!(diag.to_string(), include_str!("samples/diagnostic_01.txt"));
 }
 
@@ -231,9 +1040,11 @@ fn test_diagnostic_display_two_line() {
             20..36,
         ),
         context_name: None,
+        label: None,
+        labels: Vec::new(),
         children: Vec::new(),
     };
-    println!("{}", diag.to_string());
+    println!("{diag}");
     assert_eq!(diag.to_string(), include_str!("samples/diagnostic_02.txt"));
 }
 
@@ -247,9 +1058,11 @@ fn test_diagnostic_display_three_line() {
             38..106,
         ),
         context_name: None,
+        label: None,
+        labels: Vec::new(),
         children: Vec::new(),
     };
-    println!("{}", diag.to_string());
+    println!("{diag}");
     assert_eq!(diag.to_string(), include_str!("samples/diagnostic_03.txt"));
 }
 
@@ -261,6 +1074,8 @@ fn test_diagnostic_display_with_children() {
         message: "this is a warning".to_string(),
         span: Span::new(source.clone(), 38..106),
         context_name: None,
+        label: None,
+        labels: Vec::new(),
         children: Vec::new(),
     };
     diag.children.push(Diagnostic {
@@ -268,8 +1083,314 @@ fn test_diagnostic_display_with_children() {
         message: "this is a warning".to_string(),
         span: Span::new(source.clone(), 108..127),
         context_name: None,
+        label: None,
+        labels: Vec::new(),
         children: Vec::new(),
     });
-    println!("{}", diag.to_string());
+    println!("{diag}");
     assert_eq!(diag.to_string(), include_str!("samples/diagnostic_05.txt"));
 }
+
+#[cfg(feature = "color")]
+#[test]
+fn test_diagnostic_write_colored() {
+    let diag = Diagnostic {
+        level: DiagnosticLevel::Error,
+        message: "this is an error".to_string(),
+        span: Span::new(Rc::new(Source::from_str("this is a triumph")), 5..7),
+        context_name: Some("the thing".to_string()),
+        label: None,
+        labels: Vec::new(),
+        children: Vec::new(),
+    };
+    let mut plain = String::new();
+    diag.write_colored(&mut plain, false).unwrap();
+    assert_eq!(plain, diag.to_string());
+    let mut colored = String::new();
+    diag.write_colored(&mut colored, true).unwrap();
+    assert!(colored.starts_with("\x1b[1;31merror\x1b[0m: this is an error\n"));
+    assert!(colored.contains("\x1b[2m1 |\x1b[0m this is a triumph\n"));
+    assert!(colored.contains("\x1b[1;31m^^\x1b[0m"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_diagnostic_to_json() {
+    let source = Rc::new(Source::from_str("this is a triumph"));
+    let mut diag = Diagnostic::new(
+        DiagnosticLevel::Error,
+        Span::new(source.clone(), 5..7),
+        "this is an error",
+        Some("the thing"),
+        Vec::new(),
+    );
+    diag.children.push(Diagnostic::new(
+        DiagnosticLevel::Note,
+        Span::new(source, 10..17),
+        "this is a note",
+        Option::<String>::None,
+        Vec::new(),
+    ));
+    assert_eq!(
+        diag.to_json(),
+        concat!(
+            r#"{"level":"error","message":"this is an error","location":{"source":"the thing","#,
+            r#""byte_range":{"start":5,"end":7},"char_range":{"start":5,"end":7},"#,
+            r#""start":{"line":0,"col":5},"#,
+            r#""end":{"line":0,"col":7}},"labels":[],"children":[{"level":"note","#,
+            r#""message":"this is a note","location":{"source":"input","#,
+            r#""byte_range":{"start":10,"end":17},"char_range":{"start":10,"end":17},"#,
+            r#""start":{"line":0,"col":10},"#,
+            r#""end":{"line":0,"col":17}},"labels":[],"children":[]}]}"#,
+        )
+    );
+    let json = diag.with_primary_label("here").to_json();
+    assert!(json.contains(r#""end":{"line":0,"col":7}},"label":"here","labels":[]"#));
+}
+
+#[test]
+fn test_diagnostic_builders() {
+    let source = Rc::new(Source::from_str("let x = 1;\nlet x = 2;"));
+    let diag = Diagnostic::error(Span::new(source.clone(), 15..16), "duplicate binding")
+        .with_note(Span::new(source.clone(), 4..5), "first defined here")
+        .with_help(Span::new(source.clone(), 15..16), "consider renaming this")
+        .with_child(Diagnostic::warning(Span::new(source, 0..3), "unused"));
+    assert_eq!(diag.level(), DiagnosticLevel::Error);
+    let levels: Vec<_> = diag.children().iter().map(|child| child.level()).collect();
+    assert_eq!(
+        levels,
+        [
+            DiagnosticLevel::Note,
+            DiagnosticLevel::Help,
+            DiagnosticLevel::Warning
+        ]
+    );
+    assert_eq!(diag.children()[0].message(), "first defined here");
+    assert_eq!(diag.children()[0].span().source_text(), "x");
+}
+
+#[test]
+fn test_diagnostic_display_with_note_and_help() {
+    let source = Rc::new(Source::from_str(include_str!("samples/code_04.rs")));
+    let diag = Diagnostic::error(Span::new(source.clone(), 41..45), "this is an error")
+        .with_note(Span::new(source.clone(), 46..51), "this is a note")
+        .with_help(Span::new(source.clone(), 108..127), "this is some help");
+    println!("{diag}");
+    assert_eq!(diag.to_string(), include_str!("samples/diagnostic_06.txt"));
+}
+
+#[test]
+fn test_diagnostic_display_wide_chars() {
+    let source = Rc::new(Source::from_str("let 名前 = \"世界\";"));
+    let diag = Diagnostic::error(Span::new(source.clone(), 10..12), "this is an error")
+        .with_label(Span::new(source, 4..6), "name");
+    assert_eq!(
+        diag.to_string(),
+        concat!(
+            "error: this is an error\n",
+            " --> input:1:12\n",
+            "  |\n",
+            "1 | let 名前 = \"世界\";\n",
+            "        ----    ^^^^\n",
+            "        |\n",
+            "        name\n",
+        )
+    );
+    let text = format!("{0}x{0}", "界".repeat(100));
+    let diag = Diagnostic::error(Span::new(Rc::new(Source::from_str(text)), 100..101), "x");
+    let rendered = diag.to_string();
+    let lines: Vec<_> = rendered.lines().collect();
+    let expected = format!("1 | ...{}x{} ...", "界".repeat(30), "界".repeat(29));
+    assert_eq!(lines[3], expected);
+    assert_eq!(lines[4], format!("    {}^", " ".repeat(63)));
+}
+
+#[test]
+fn test_diagnostic_display_zero_width_chars() {
+    let source = Rc::new(Source::from_str("\"🚀\" + \"e\u{301}\u{200B}\" + x"));
+    let diag = Diagnostic::error(Span::new(source.clone(), 14..15), "this is an error")
+        .with_label(Span::new(source, 7..10), "accent");
+    assert_eq!(
+        diag.to_string(),
+        concat!(
+            "error: this is an error\n",
+            " --> input:1:13\n",
+            "  |\n",
+            "1 | \"🚀\" + \"e\u{301}\u{200B}\" + x\n",
+            "            -    ^\n",
+            "            |\n",
+            "            accent\n",
+        )
+    );
+}
+
+#[test]
+fn test_diagnostic_display_long_line() {
+    let text = format!("{}needle{}\nshort", "a".repeat(200), "b".repeat(200));
+    let source = Rc::new(Source::from_str(text));
+    let diag = Diagnostic::error(Span::new(source.clone(), 200..206), "found it")
+        .with_primary_label("here");
+    let rendered = diag.to_string();
+    let lines: Vec<_> = rendered.lines().collect();
+    let expected = format!("1 | ...{}needle{}...", "a".repeat(57), "b".repeat(57));
+    assert_eq!(lines[3], expected);
+    assert_eq!(lines[4], format!("    {}^^^^^^ here", " ".repeat(60)));
+    let mut output = String::new();
+    let options = RenderOptions {
+        max_line_width: None,
+        ..RenderOptions::default()
+    };
+    diag.write_with(&mut output, options).unwrap();
+    assert!(output.contains(&format!(
+        "1 | {}needle{}\n",
+        "a".repeat(200),
+        "b".repeat(200)
+    )));
+    let diag = Diagnostic::error(Span::new(source, 0..3), "at the start");
+    let rendered = diag.to_string();
+    let lines: Vec<_> = rendered.lines().collect();
+    assert_eq!(lines[3], format!("1 | {}...", "a".repeat(120)));
+    assert_eq!(lines[4], "    ^^^");
+}
+
+#[test]
+fn test_diagnostic_display_with_tabs() {
+    let source = Rc::new(Source::from_str("fn main() {\n\tlet x =\t1;\n}"));
+    let diag = Diagnostic::error(Span::new(source.clone(), 17..22), "this is an error");
+    assert_eq!(diag.to_string(), include_str!("samples/diagnostic_07.txt"));
+    let mut output = String::new();
+    diag.write_with(
+        &mut output,
+        RenderOptions {
+            tab_width: 8,
+            ..RenderOptions::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        output,
+        "error: this is an error\n --> input:2:12\n  |\n2 |         let x = 1;\n                ^^^^^\n"
+    );
+}
+
+#[test]
+fn test_diagnostic_display_with_labels() {
+    let source = Rc::new(Source::from_str(include_str!("samples/code_04.rs")));
+    let diag = Diagnostic::error(Span::new(source.clone(), 41..45), "this is an error")
+        .with_label(Span::new(source.clone(), 46..51), "this is a label")
+        .with_label(Span::new(source.clone(), 38..40), "this is another label")
+        .with_spans(Span::new(source.clone(), 0..4));
+    println!("{diag}");
+    assert_eq!(diag.to_string(), include_str!("samples/diagnostic_08.txt"));
+    assert_eq!(diag.labels().len(), 3);
+    assert_eq!(diag.labels()[2].1, None);
+}
+
+#[test]
+fn test_diagnostic_display_with_overlapping_labels() {
+    let source = Rc::new(Source::from_str("let value = left + right;"));
+    let diag = Diagnostic::error(Span::new(source.clone(), 12..16), "this is an error")
+        .with_label(Span::new(source.clone(), 4..17), "overlaps")
+        .with_label(Span::new(source.clone(), 17..18), "adjacent")
+        .with_label(Span::new(source.clone(), 19..24), "right");
+    println!("{diag}");
+    assert_eq!(diag.to_string(), include_str!("samples/diagnostic_09.txt"));
+}
+
+#[test]
+fn test_diagnostic_display_with_primary_label() {
+    let source = Rc::new(Source::from_str("fn main() {\n    foo bar\n}"));
+    let diag = Diagnostic::error(Span::new(source.clone(), 20..23), "unexpected token")
+        .with_primary_label("unexpected token here");
+    println!("{diag}");
+    assert_eq!(diag.to_string(), include_str!("samples/diagnostic_10.txt"));
+    assert_eq!(diag.primary_label(), Some("unexpected token here"));
+    let diag = diag.with_label(Span::new(source, 16..19), "after this");
+    assert_eq!(
+        diag.to_string(),
+        concat!(
+            "error: unexpected token\n",
+            " --> input:2:8\n",
+            "  |\n",
+            "2 |     foo bar\n",
+            "        --- ^^^ unexpected token here\n",
+            "        |\n",
+            "        after this\n",
+        )
+    );
+}
+
+#[test]
+fn test_diagnostic_merged_spans_across_sources() {
+    let main = Rc::new(Source::from_str("include!(\"other\");\nfoo();"));
+    let other = Rc::new(Source::from_str("fn included() {}"));
+    let diag = Diagnostic::error(Span::new(main.clone(), 0..8), "this is an error")
+        .with_note(Span::new(other.clone(), 3..11), "defined here")
+        .with_help(Span::new(main.clone(), 20..25), "this is some help")
+        .with_child(
+            Diagnostic::warning(Span::new(other.clone(), 0..2), "this is a warning")
+                .with_note(Span::blank(), "this is a note"),
+        );
+    assert_eq!(diag.merged_span(), Span::new(main.clone(), 0..25));
+    assert_eq!(
+        diag.merged_spans(),
+        vec![Span::new(main, 0..25), Span::new(other, 0..11)]
+    );
+}
+
+#[test]
+fn test_diagnostic_display_source_name() {
+    let source = Rc::new(Source::from_str("this is a triumph").with_name("the thing"));
+    let diag = Diagnostic::error(Span::new(source.clone(), 5..7), "this is an error");
+    assert_eq!(diag.context_name(), "the thing");
+    assert_eq!(diag.to_string(), include_str!("samples/diagnostic_01.txt"));
+    let mut diag = diag;
+    diag.set_context_name(Some("override"));
+    assert!(diag.to_string().contains(" --> override:1:5\n"));
+}
+
+#[test]
+fn test_diagnostic_short() {
+    let source = Rc::new(Source::from_str("a\nbc 12x"));
+    let diag = Diagnostic::error(Span::new(source.clone(), 7..8), "expected digit")
+        .with_note(Span::new(source, 0..1), "ignored");
+    assert_eq!(diag.short(), "error at input:2:5: expected digit");
+    let err = ParseStream::from("x").parse_digit().unwrap_err();
+    assert_eq!(err.short(), "error at input:1:0: expected digit (0-9)");
+    let diag = Diagnostic::error(Span::new(Rc::new(Source::from_str("\t\tx")), 2..3), "tabs");
+    assert_eq!(diag.short(), "error at input:1:8: tabs");
+    assert!(diag.to_string().contains(" --> input:1:8\n"));
+}
+
+#[test]
+fn test_diagnostic_line_col_range() {
+    let source = Rc::new(Source::from_str("one\ntwo\nthree"));
+    let diag = Diagnostic::error(Span::new(source.clone(), 4..7), "primary");
+    assert_eq!(
+        diag.line_col_range(),
+        (LineCol { line: 1, col: 0 }, LineCol { line: 1, col: 3 })
+    );
+    let diag = diag.with_note(Span::new(source, 8..13), "later");
+    assert_eq!(
+        diag.line_col_range(),
+        (LineCol { line: 1, col: 0 }, LineCol { line: 2, col: 5 })
+    );
+}
+
+#[test]
+fn test_diagnostic_level_ordering() {
+    use DiagnosticLevel::*;
+    let mut levels = vec![Note, Error, Help, Warning];
+    levels.sort();
+    assert_eq!(levels, [Help, Note, Warning, Error]);
+    assert!(Warning >= Warning && Error > Warning && Note < Warning);
+    let span = Span::blank();
+    let diag = Diagnostic::new(Note, span.clone(), "a", None::<&str>, vec![]);
+    assert_eq!(diag.max_level(), Note);
+    let diag = diag.with_help(span.clone(), "b").with_child(
+        Diagnostic::new(Help, span.clone(), "c", None::<&str>, vec![])
+            .with_child(Diagnostic::warning(span, "d")),
+    );
+    assert_eq!(diag.level(), Note);
+    assert_eq!(diag.max_level(), Warning);
+}
@@ -0,0 +1,264 @@
+//! Home of [`IndentTracker`], for parsing grammars that follow the off-side rule.
+
+use core::ops::{Deref, DerefMut};
+
+use super::*;
+
+/// How the indentation of a line compares to the enclosing indentation levels, as returned by
+/// [`IndentTracker::parse_indentation`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum IndentChange {
+    /// The line is indented further than the previous level, which has been pushed as a new
+    /// level (a synthetic `INDENT`).
+    Indent,
+    /// The line is indented the same as the current level.
+    Same,
+    /// The line returns to an outer level, closing the given number of levels (that many
+    /// synthetic `DEDENT`s).
+    Dedent(usize),
+}
+
+/// Wraps a [`ParseStream`] with a stack of indentation levels, for parsing Python or YAML-like
+/// grammars in which blocks are delimited by indentation rather than brackets.
+///
+/// [`IndentTracker`] dereferences to its [`ParseStream`], so everything else is parsed as
+/// usual. At the start of each line, call [`IndentTracker::parse_indentation`] to consume the
+/// leading spaces and tabs and find out whether a block was opened or closed. Blank and
+/// whitespace-only lines never open or close a block.
+///
+/// ```
+/// use quoth::*;
+///
+/// let mut tracker = IndentTracker::new("a\n  b\n  c\nd");
+/// let mut changes = Vec::new();
+/// loop {
+///     changes.push(tracker.parse_indentation().unwrap());
+///     tracker.consume(1).unwrap();
+///     if tracker.parse_str("\n").is_err() {
+///         break;
+///     }
+/// }
+/// use IndentChange::*;
+/// assert_eq!(changes, [Same, Indent, Same, Dedent(1)]);
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct IndentTracker {
+    stream: ParseStream,
+    stack: Vec<usize>,
+    tab_width: usize,
+}
+
+impl IndentTracker {
+    /// Creates a new [`IndentTracker`] over the given [`ParseStream`], starting with a single
+    /// indentation level of zero and a tab width of [`DEFAULT_TAB_WIDTH`].
+    pub fn new(stream: impl Into<ParseStream>) -> IndentTracker {
+        IndentTracker {
+            stream: stream.into(),
+            stack: vec![0],
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
+
+    /// Sets the number of columns between tab stops used when measuring indentation, so a tab
+    /// advances the indentation to the next multiple of `tab_width`.
+    pub fn with_tab_width(mut self, tab_width: usize) -> IndentTracker {
+        self.tab_width = tab_width;
+        self
+    }
+
+    /// Consumes this [`IndentTracker`], returning the underlying [`ParseStream`].
+    pub fn into_inner(self) -> ParseStream {
+        self.stream
+    }
+
+    /// Returns the current (innermost) indentation level, in columns.
+    pub fn current_indent(&self) -> usize {
+        *self.stack.last().unwrap()
+    }
+
+    /// Returns the stack of open indentation levels, outermost first. The outermost level is
+    /// always zero.
+    pub fn levels(&self) -> &[usize] {
+        &self.stack
+    }
+
+    /// Pushes a new indentation level, which must be deeper than the current one.
+    ///
+    /// Returns an error at the current position of the [`ParseStream`] if `indent` is not
+    /// greater than [`IndentTracker::current_indent`], in which case the indentation levels
+    /// are not changed.
+    pub fn push_indent(&mut self, indent: usize) -> Result<()> {
+        let current = self.current_indent();
+        if indent <= current {
+            return Err(Error::new(
+                self.stream.current_span(),
+                format!(
+                    "inconsistent indentation: {} must be deeper than the current level of {}",
+                    columns(indent),
+                    columns(current)
+                ),
+            ));
+        }
+        self.stack.push(indent);
+        Ok(())
+    }
+
+    /// Pops the current indentation level, returning it, or returns `None` if only the
+    /// outermost level remains.
+    pub fn pop_indent(&mut self) -> Option<usize> {
+        if self.stack.len() == 1 {
+            return None;
+        }
+        self.stack.pop()
+    }
+
+    /// Consumes the spaces and tabs at the start of the current line, returning the width of
+    /// the indentation in columns along with its [`Span`].
+    ///
+    /// Tabs count up to the next multiple of the tab width. Returns an error if the
+    /// [`ParseStream`] is not at the start of a line. The indentation levels are not affected.
+    pub fn parse_indent(&mut self) -> Result<(usize, Span)> {
+        if !self.stream.at_line_start() {
+            return Err(Error::new(
+                self.stream.current_span(),
+                "expected indentation at the start of a line",
+            ));
+        }
+        let span = self
+            .stream
+            .recover_while(|stream| matches!(stream.peek_char(), Some(' ' | '\t')));
+        Ok((span.visual_end(self.tab_width).col, span))
+    }
+
+    /// Consumes the indentation at the start of the current line (see
+    /// [`IndentTracker::parse_indent`]) and compares it to the current indentation levels,
+    /// pushing or popping levels accordingly.
+    ///
+    /// A blank or whitespace-only line returns [`IndentChange::Same`] without changing the
+    /// indentation levels, since its indentation is meaningless.
+    ///
+    /// Returns an error spanning the indentation if it returns to a level that was never
+    /// opened, e.g. a line indented by 3 columns directly inside a block indented by 4. In
+    /// that case neither the [`ParseStream`] nor the indentation levels are changed.
+    pub fn parse_indentation(&mut self) -> Result<IndentChange> {
+        let position = self.stream.position;
+        let (indent, span) = self.parse_indent()?;
+        if matches!(self.stream.peek_char(), None | Some('\n' | '\r')) {
+            return Ok(IndentChange::Same);
+        }
+        let current = self.current_indent();
+        if indent > current {
+            self.stack.push(indent);
+            return Ok(IndentChange::Indent);
+        }
+        if indent == current {
+            return Ok(IndentChange::Same);
+        }
+        let Some(level) = self.stack.iter().rposition(|&level| level == indent) else {
+            self.stream.position = position;
+            let outer = self.stack.iter().rev().find(|&&level| level < indent);
+            return Err(Error::new(
+                span,
+                format!(
+                    "inconsistent indentation: {} does not match any enclosing level \
+                     (expected {} or {current})",
+                    columns(indent),
+                    outer.unwrap_or(&0)
+                ),
+            ));
+        };
+        let closed = self.stack.len() - level - 1;
+        self.stack.truncate(level + 1);
+        Ok(IndentChange::Dedent(closed))
+    }
+}
+
+/// Formats a width in columns, e.g. `1 column` or `4 columns`.
+fn columns(width: usize) -> String {
+    match width {
+        1 => "1 column".to_string(),
+        width => format!("{width} columns"),
+    }
+}
+
+impl Deref for IndentTracker {
+    type Target = ParseStream;
+
+    fn deref(&self) -> &Self::Target {
+        &self.stream
+    }
+}
+
+impl DerefMut for IndentTracker {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.stream
+    }
+}
+
+#[test]
+fn test_indent_tracker_parse_indent() {
+    let mut tracker = IndentTracker::new("\t  x\n    y").with_tab_width(4);
+    let (indent, span) = tracker.parse_indent().unwrap();
+    assert_eq!((indent, span.byte_range()), (6, &(0..3)));
+    assert_eq!(tracker.remaining(), "x\n    y");
+    let err = tracker.parse_indent().unwrap_err();
+    assert!(err.message().contains("start of a line"));
+    tracker.position = 5;
+    assert_eq!(tracker.parse_indent().unwrap().0, 4);
+    assert_eq!(tracker.levels(), &[0]);
+}
+
+#[test]
+fn test_indent_tracker_parse_indentation() {
+    let mut tracker = IndentTracker::new("a\n  b\n    c\nd\n  e\n g");
+    let mut changes = Vec::new();
+    let err = loop {
+        match tracker.parse_indentation() {
+            Ok(change) => changes.push(change),
+            Err(err) => break err,
+        }
+        tracker.consume(2).unwrap();
+    };
+    use IndentChange::*;
+    assert_eq!(changes, [Same, Indent, Indent, Dedent(2), Indent]);
+    assert_eq!(err.span().source_text(), " ");
+    assert_eq!(
+        err.message(),
+        "inconsistent indentation: 1 column does not match any enclosing level (expected 0 or 2)"
+    );
+    assert_eq!(tracker.remaining(), " g");
+    assert_eq!(tracker.current_indent(), 2);
+    assert_eq!(tracker.pop_indent(), Some(2));
+    assert_eq!(tracker.pop_indent(), None);
+    tracker.push_indent(8).unwrap();
+    let err = tracker.push_indent(8).unwrap_err();
+    assert_eq!(
+        err.message(),
+        "inconsistent indentation: 8 columns must be deeper than the current level of 8 columns"
+    );
+    assert_eq!(err.span().source_text(), " ");
+    assert_eq!(tracker.levels(), &[0, 8]);
+    assert_eq!(tracker.into_inner().position, 18);
+}
+
+#[test]
+fn test_indent_tracker_blank_lines() {
+    let mut tracker = IndentTracker::new(
+        "a
+  b
+
+ 
+  c
+d",
+    );
+    let mut changes = Vec::new();
+    loop {
+        changes.push(tracker.parse_indentation().unwrap());
+        tracker.recover_while(|stream| !stream.peek_char_eq('\n'));
+        if tracker.parse_str("\n").is_err() {
+            break;
+        }
+    }
+    use IndentChange::*;
+    assert_eq!(changes, [Same, Indent, Same, Same, Same, Dedent(1)]);
+}
@@ -0,0 +1,423 @@
+//! Extensions to [`IndexedStr`] from the `safe-string` crate.
+
+use core::ops::Range;
+
+use super::*;
+
+/// Additional methods for all [`IndexedStr`] types, i.e. [`IndexedString`] and
+/// [`IndexedSlice`].
+///
+/// All indices accepted and returned by these methods are _character_ indices, consistent
+/// with [`IndexedStr::slice`] and [`IndexedStr::char_at`], rather than byte offsets.
+pub trait IndexedStrExt: IndexedStr {
+    /// Returns the character index of the first occurrence of `pat`, if any.
+    ///
+    /// ```
+    /// use quoth::*;
+    ///
+    /// let s = IndexedString::from_str("héllo wörld");
+    /// let i = s.find("wö").unwrap();
+    /// assert_eq!(i, 6);
+    /// assert_eq!(s.slice(i..), "wörld");
+    /// ```
+    fn find(&self, pat: &str) -> Option<usize> {
+        let text = self.as_str();
+        text.find(pat).map(|byte| char_index(text, byte))
+    }
+
+    /// Returns the character index of the last occurrence of `pat`, if any.
+    fn rfind(&self, pat: &str) -> Option<usize> {
+        let text = self.as_str();
+        text.rfind(pat).map(|byte| char_index(text, byte))
+    }
+
+    /// Returns `true` if `pat` occurs anywhere within this [`IndexedStr`].
+    fn contains(&self, pat: &str) -> bool {
+        self.as_str().contains(pat)
+    }
+
+    /// Returns an iterator over the lines of this [`IndexedStr`], as [`IndexedSlice`]s.
+    ///
+    /// Lines are split on `\n`, and a `\r` immediately before the `\n` is stripped. As with
+    /// [`str::lines`], a trailing newline does not produce an extra empty line, and an empty
+    /// string has no lines at all.
+    ///
+    /// This differs from [`IndexedStr::lines`], which keeps `\r`, always yields a final
+    /// (possibly empty) line after the last `\n`, and, for an [`IndexedSlice`], continues past
+    /// the end of the slice to the end of the underlying [`IndexedString`].
+    ///
+    /// ```
+    /// use quoth::*;
+    ///
+    /// let s = IndexedString::from_str("α\r\nβ\n\nγ\n");
+    /// let lines: Vec<_> = s.split_lines().map(|line| line.to_string()).collect();
+    /// assert_eq!(lines, ["α", "β", "", "γ"]);
+    /// assert_eq!(s.line_count(), 4);
+    /// ```
+    fn split_lines(&self) -> SplitLines<'_, Self> {
+        SplitLines {
+            text: self,
+            start: 0,
+        }
+    }
+
+    /// Returns an iterator over the pieces of this [`IndexedStr`] separated by `sep`, as
+    /// [`IndexedSlice`]s.
+    ///
+    /// As with [`str::split`], consecutive separators (or a separator at the start or end)
+    /// produce empty pieces.
+    ///
+    /// ```
+    /// use quoth::*;
+    ///
+    /// let s = IndexedString::from_str("α,,β,");
+    /// let pieces: Vec<_> = s.split(',').map(|piece| piece.to_string()).collect();
+    /// assert_eq!(pieces, ["α", "", "β", ""]);
+    /// ```
+    fn split(&self, sep: char) -> Split<'_, Self> {
+        Split {
+            text: self,
+            sep,
+            start: Some(0),
+        }
+    }
+
+    /// Returns an iterator over the non-empty runs of non-whitespace characters (as
+    /// defined by [`char::is_whitespace`]) of this [`IndexedStr`], as [`IndexedSlice`]s.
+    fn split_whitespace(&self) -> SplitWhitespace<'_, Self> {
+        SplitWhitespace {
+            text: self,
+            start: 0,
+        }
+    }
+
+    /// Returns the range of character indices that this [`IndexedStr`] occupies within
+    /// `parent`, or `None` if it is not a slice of `parent`.
+    ///
+    /// This maps an [`IndexedSlice`] (e.g. one found within [`Span::source_text`]) back to
+    /// absolute positions in the [`IndexedString`] it was sliced from, such as a [`Source`],
+    /// so that it can be turned back into a [`Span`]. Slices are matched by the memory they
+    /// point to, so a copy of the same text elsewhere is not considered part of `parent`.
+    ///
+    /// ```
+    /// use quoth::*;
+    ///
+    /// let s = IndexedString::from_str("héllo wörld");
+    /// let rest = s.slice(6..);
+    /// let word = rest.split_whitespace().next().unwrap();
+    /// assert_eq!(word.range_within(&s), Some(6..11));
+    /// assert_eq!(word.byte_range_within(&s), Some(7..13));
+    /// assert_eq!(IndexedString::from_str("wörld").range_within(&s), None);
+    /// ```
+    fn range_within(&self, parent: &impl IndexedStr) -> Option<Range<usize>> {
+        let start = offset_within(self.chars(), parent.chars())?;
+        Some(start..start + self.len())
+    }
+
+    /// Returns the range of UTF-8 byte offsets that this [`IndexedStr`] occupies within
+    /// `parent`, or `None` if it is not a slice of `parent`. See
+    /// [`IndexedStrExt::range_within`].
+    fn byte_range_within(&self, parent: &impl IndexedStr) -> Option<Range<usize>> {
+        let text = self.as_str().as_bytes();
+        let start = offset_within(text, parent.as_str().as_bytes())?;
+        Some(start..start + text.len())
+    }
+
+    /// Returns the number of lines in this [`IndexedStr`], as yielded by
+    /// [`IndexedStrExt::split_lines`].
+    fn line_count(&self) -> usize {
+        self.split_lines().count()
+    }
+
+    /// Returns `true` if this [`IndexedStr`] is equal to `other`, ignoring case.
+    ///
+    /// Characters are compared by their full Unicode lowercase mappings (see
+    /// [`char::to_lowercase`]), without allocating lowercased copies of either string.
+    ///
+    /// ```
+    /// use quoth::*;
+    ///
+    /// let s = IndexedString::from_str("Straße ÀB");
+    /// assert!(!s.eq_ignore_case("STRASSE àb"));
+    /// assert!(s.eq_ignore_case("straße àb"));
+    /// assert!(s.slice(7..).eq_ignore_case("àb"));
+    /// ```
+    fn eq_ignore_case(&self, other: &str) -> bool {
+        let text = self.as_str();
+        text.chars()
+            .flat_map(char::to_lowercase)
+            .eq(other.chars().flat_map(char::to_lowercase))
+    }
+
+    /// Returns `true` if this [`IndexedStr`] is equal to `other`, ignoring case and accents.
+    ///
+    /// Accents are removed from precomposed Latin letters (e.g. `é` compares equal to `e`), and
+    /// combining diacritical marks (U+0300 to U+036F) are ignored, so decomposed text compares
+    /// equal to its precomposed form. Other letters, such as `ß` or accented letters outside
+    /// the Latin-1 Supplement and Latin Extended-A blocks, are only compared ignoring case.
+    ///
+    /// Requires the `unicode` feature.
+    ///
+    /// ```
+    /// use quoth::*;
+    ///
+    /// let s = IndexedString::from_str("Crème Brûlée");
+    /// assert!(s.eq_ignore_case_accents("creme brulee"));
+    /// assert!(s.eq_ignore_case_accents("CRE\u{300}ME BRULEE"));
+    /// assert!(!s.eq_ignore_case_accents("creme brule"));
+    /// ```
+    #[cfg(feature = "unicode")]
+    fn eq_ignore_case_accents(&self, other: &str) -> bool {
+        fn fold(text: &str) -> impl Iterator<Item = char> + '_ {
+            text.chars()
+                .filter(|c| !is_combining_mark(*c))
+                .map(strip_accent)
+                .flat_map(char::to_lowercase)
+        }
+        fold(self.as_str()).eq(fold(other))
+    }
+}
+
+impl<T: IndexedStr + ?Sized> IndexedStrExt for T {}
+
+/// An iterator over the lines of an [`IndexedStr`], see [`IndexedStrExt::split_lines`].
+pub struct SplitLines<'a, T: IndexedStr + ?Sized> {
+    text: &'a T,
+    start: usize,
+}
+
+impl<'a, T: IndexedStr + ?Sized> Iterator for SplitLines<'a, T> {
+    type Item = IndexedSlice<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chars = self.text.chars();
+        let start = self.start;
+        if start >= chars.len() {
+            return None;
+        }
+        let end = match chars[start..].iter().position(|c| *c == '\n') {
+            Some(newline) => {
+                let newline = start + newline;
+                self.start = newline + 1;
+                match newline > start && chars[newline - 1] == '\r' {
+                    true => newline - 1,
+                    false => newline,
+                }
+            }
+            None => {
+                self.start = chars.len();
+                chars.len()
+            }
+        };
+        Some(self.text.slice(start..end))
+    }
+}
+
+/// An iterator over the pieces of an [`IndexedStr`] separated by a character, see
+/// [`IndexedStrExt::split`].
+pub struct Split<'a, T: IndexedStr + ?Sized> {
+    text: &'a T,
+    sep: char,
+    start: Option<usize>,
+}
+
+impl<'a, T: IndexedStr + ?Sized> Iterator for Split<'a, T> {
+    type Item = IndexedSlice<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.start?;
+        let chars = self.text.chars();
+        let end = match chars[start..].iter().position(|c| *c == self.sep) {
+            Some(sep) => {
+                self.start = Some(start + sep + 1);
+                start + sep
+            }
+            None => {
+                self.start = None;
+                chars.len()
+            }
+        };
+        Some(self.text.slice(start..end))
+    }
+}
+
+/// An iterator over the whitespace-separated pieces of an [`IndexedStr`], see
+/// [`IndexedStrExt::split_whitespace`].
+pub struct SplitWhitespace<'a, T: IndexedStr + ?Sized> {
+    text: &'a T,
+    start: usize,
+}
+
+impl<'a, T: IndexedStr + ?Sized> Iterator for SplitWhitespace<'a, T> {
+    type Item = IndexedSlice<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chars = self.text.chars();
+        let start = self.start
+            + chars[self.start..]
+                .iter()
+                .position(|c| !c.is_whitespace())?;
+        let end = chars[start..]
+            .iter()
+            .position(|c| c.is_whitespace())
+            .map_or(chars.len(), |len| start + len);
+        self.start = end;
+        Some(self.text.slice(start..end))
+    }
+}
+
+/// Returns the index of the first element of `slice` within `parent`, if `slice` points into
+/// the memory of `parent`.
+fn offset_within<T>(slice: &[T], parent: &[T]) -> Option<usize> {
+    let (inner, outer) = (slice.as_ptr_range(), parent.as_ptr_range());
+    if inner.start < outer.start || inner.end > outer.end {
+        return None;
+    }
+    Some((inner.start as usize - outer.start as usize) / size_of::<T>().max(1))
+}
+
+/// Returns `true` if `c` is in the Combining Diacritical Marks block.
+#[cfg(feature = "unicode")]
+fn is_combining_mark(c: char) -> bool {
+    matches!(c, '\u{300}'..='\u{36f}')
+}
+
+/// Returns the lowercase base letter of a precomposed accented Latin letter from the Latin-1
+/// Supplement or Latin Extended-A blocks, or `c` itself if it isn't one.
+#[cfg(feature = "unicode")]
+fn strip_accent(c: char) -> char {
+    match c {
+        'À'..='Å' | 'à'..='å' | '\u{100}'..='\u{105}' => 'a',
+        'Ç' | 'ç' | '\u{106}'..='\u{10d}' => 'c',
+        '\u{10e}'..='\u{111}' => 'd',
+        'È'..='Ë' | 'è'..='ë' | '\u{112}'..='\u{11b}' => 'e',
+        '\u{11c}'..='\u{123}' => 'g',
+        '\u{124}'..='\u{127}' => 'h',
+        'Ì'..='Ï' | 'ì'..='ï' | '\u{128}'..='\u{131}' => 'i',
+        '\u{134}'..='\u{135}' => 'j',
+        '\u{136}'..='\u{137}' => 'k',
+        '\u{139}'..='\u{142}' => 'l',
+        'Ñ' | 'ñ' | '\u{143}'..='\u{148}' => 'n',
+        'Ò'..='Ö' | 'Ø' | 'ò'..='ö' | 'ø' | '\u{14c}'..='\u{151}' => 'o',
+        '\u{154}'..='\u{159}' => 'r',
+        '\u{15a}'..='\u{161}' => 's',
+        '\u{162}'..='\u{167}' => 't',
+        'Ù'..='Ü' | 'ù'..='ü' | '\u{168}'..='\u{173}' => 'u',
+        '\u{174}'..='\u{175}' => 'w',
+        'Ý' | 'ý' | 'ÿ' | '\u{176}'..='\u{178}' => 'y',
+        '\u{179}'..='\u{17e}' => 'z',
+        _ => c,
+    }
+}
+
+/// Converts a byte offset on a character boundary of `text` into a character index.
+fn char_index(text: &str, byte: usize) -> usize {
+    text[..byte].chars().count()
+}
+
+#[test]
+fn test_indexed_str_find() {
+    let s = IndexedString::from_str("αβγ abc αβγ");
+    assert_eq!(s.find("αβγ"), Some(0));
+    assert_eq!(s.rfind("αβγ"), Some(8));
+    assert_eq!(s.find("abc"), Some(4));
+    assert_eq!(s.slice(s.find("abc").unwrap()..7), "abc");
+    assert_eq!(s.find("xyz"), None);
+    assert_eq!(s.rfind("xyz"), None);
+    assert_eq!(s.find(""), Some(0));
+    assert_eq!(s.rfind(""), Some(11));
+    assert!(s.contains("c α"));
+    assert!(!s.contains("cα"));
+    assert!(s.starts_with("αβ"));
+    assert!(s.ends_with("βγ"));
+    let slice = s.slice(4..);
+    assert_eq!(slice.find("αβγ"), Some(4));
+    assert_eq!(slice.slice(slice.find("αβγ").unwrap()..), "αβγ");
+}
+
+#[test]
+fn test_indexed_str_split_lines() {
+    let lines = |s: &str| -> Vec<String> {
+        IndexedString::from_str(s)
+            .split_lines()
+            .map(|line| line.to_string())
+            .collect()
+    };
+    assert_eq!(lines(""), Vec::<String>::new());
+    assert_eq!(lines("\n"), [""]);
+    assert_eq!(lines("é"), ["é"]);
+    assert_eq!(lines("é\n"), ["é"]);
+    assert_eq!(lines("é\n\n"), ["é", ""]);
+    assert_eq!(lines("é\r\nü\rx\n"), ["é", "ü\rx"]);
+    assert_eq!(lines("a\nb"), ["a", "b"]);
+    assert_eq!(IndexedString::from_str("a\nb\n").line_count(), 2);
+    let s = IndexedString::from_str("zero\nóne\ntwo\nthree");
+    let slice = s.slice(5..11);
+    assert_eq!(slice, "óne\ntw");
+    let lines: Vec<_> = slice.split_lines().collect();
+    assert_eq!(lines, ["óne", "tw"]);
+    assert_eq!(lines[0].chars(), &['ó', 'n', 'e']);
+    assert_eq!(slice.line_count(), 2);
+}
+
+#[test]
+fn test_indexed_str_split() {
+    let pieces = |s: &IndexedSlice, sep| -> Vec<String> {
+        s.split(sep).map(|piece| piece.to_string()).collect()
+    };
+    let s = IndexedString::from_str("[ü::ö:ä:]");
+    let inner = s.slice(1..8);
+    assert_eq!(pieces(&inner, ':'), ["ü", "", "ö", "ä", ""]);
+    assert_eq!(pieces(&inner, '😀'), ["ü::ö:ä:"]);
+    assert_eq!(pieces(&s.slice(0..0), ':'), [""]);
+    assert_eq!(pieces(&s.slice(2..3), ':'), ["", ""]);
+    let last = inner.split(':').nth(3).unwrap();
+    assert_eq!(last.chars(), &['ä']);
+}
+
+#[test]
+fn test_indexed_str_split_whitespace() {
+    let s = IndexedString::from_str("  héllo \t\n wörld\u{3000}日本  ");
+    let pieces: Vec<_> = s.split_whitespace().map(|p| p.to_string()).collect();
+    assert_eq!(pieces, ["héllo", "wörld", "日本"]);
+    assert_eq!(IndexedString::from_str("").split_whitespace().count(), 0);
+    assert_eq!(
+        IndexedString::from_str(" \t ").split_whitespace().count(),
+        0
+    );
+    assert_eq!(IndexedString::from_str("x").split_whitespace().count(), 1);
+}
+
+#[test]
+fn test_indexed_str_range_within() {
+    let s = IndexedString::from_str("αβ, γδ");
+    let inner = s.slice(4..);
+    assert_eq!(inner.range_within(&s), Some(4..6));
+    assert_eq!(inner.byte_range_within(&s), Some(6..10));
+    assert_eq!(inner.slice(1..).range_within(&inner), Some(1..2));
+    assert_eq!(inner.slice(1..).range_within(&s), Some(5..6));
+    assert_eq!(s.slice(2..2).range_within(&s), Some(2..2));
+    assert_eq!(s.range_within(&inner), None);
+    assert_eq!(s.clone().range_within(&s), None);
+}
+
+#[test]
+fn test_indexed_str_eq_ignore_case() {
+    let s = IndexedString::from_str("Hello WÖRLD");
+    assert!(s.eq_ignore_case("hello wörld"));
+    assert!(s.slice(6..).eq_ignore_case("Wörld"));
+    assert!(!s.eq_ignore_case("hello world"));
+    assert!(!s.eq_ignore_case("hello wörl"));
+    assert!(IndexedString::from_str("").eq_ignore_case(""));
+}
+
+#[cfg(feature = "unicode")]
+#[test]
+fn test_indexed_str_eq_ignore_case_accents() {
+    let s = IndexedString::from_str("Ångström Łódź");
+    assert!(s.eq_ignore_case_accents("angstrom lodz"));
+    assert!(s.eq_ignore_case_accents("A\u{30a}NGSTRO\u{308}M LODZ"));
+    assert!(!s.eq_ignore_case_accents("angstrom lod"));
+    assert!(IndexedString::from_str("Straße").eq_ignore_case_accents("STRAßE"));
+    assert!(!IndexedString::from_str("Straße").eq_ignore_case_accents("strasse"));
+}
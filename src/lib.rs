@@ -6,6 +6,12 @@ mod span;
 pub use span::*;
 mod diagnostic;
 pub use diagnostic::*;
+mod indexed;
+pub use indexed::*;
+mod indent;
+pub use indent::*;
+mod pratt;
+pub use pratt::*;
 mod parsing;
 pub use parsing::*;
 pub mod parsable;
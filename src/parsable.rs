@@ -2,15 +2,31 @@
 
 use super::*;
 
+mod comments;
+mod error_node;
 mod everything;
 mod exact;
+mod ident;
+mod key_values;
+mod list;
+mod non_empty;
 mod nothing;
 pub mod numbers;
 mod optional;
+mod strings;
+mod trivia;
 mod whitespace;
 
+pub use comments::*;
+pub use error_node::*;
 pub use everything::*;
 pub use exact::*;
+pub use ident::*;
+pub use key_values::*;
+pub use list::*;
+pub use non_empty::*;
 pub use nothing::*;
 pub use optional::*;
+pub use strings::*;
+pub use trivia::*;
 pub use whitespace::*;
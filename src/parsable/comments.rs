@@ -0,0 +1,155 @@
+use super::*;
+
+use crate as quoth;
+
+/// A comment running from a start token (`//` by default) through the end of the line.
+///
+/// The terminating newline (`\n` or `\r\n`) is not consumed and is not part of the comment.
+/// Use [`LineComment::parse_with`] to parse comments with a different start token, such as
+/// `#`.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct LineComment {
+    #[quoth(span)]
+    span: Span,
+    body: Span,
+}
+
+impl LineComment {
+    /// Parses a [`LineComment`] starting with the specified token from the [`ParseStream`].
+    pub fn parse_with(stream: &mut ParseStream, start: impl ToString) -> Result<Self> {
+        let start_position = stream.position;
+        stream.parse_str(start)?;
+        let body_position = stream.position;
+        while let Some(c) = stream.peek_char() {
+            if c == '\n' || (c == '\r' && stream.peek_nth(1) == Some('\n')) {
+                break;
+            }
+            stream.position += 1;
+        }
+        Ok(LineComment {
+            span: stream.span_from(start_position),
+            body: stream.span_from(body_position),
+        })
+    }
+
+    /// Returns the [`Span`] of the text of this comment, excluding the start token.
+    pub fn body(&self) -> &Span {
+        &self.body
+    }
+}
+
+impl Parsable for LineComment {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        LineComment::parse_with(stream, "//")
+    }
+}
+
+/// A comment enclosed in a pair of delimiters, `/*` and `*/` by default, which may span
+/// multiple lines.
+///
+/// By default block comments nest, so `/* a /* b */ c */` is a single comment. Use
+/// [`BlockComment::parse_with`] to parse comments with different delimiters or without
+/// nesting.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct BlockComment {
+    #[quoth(span)]
+    span: Span,
+    body: Span,
+}
+
+impl BlockComment {
+    /// Parses a [`BlockComment`] delimited by `open` and `close` from the [`ParseStream`].
+    ///
+    /// If `nested` is `true`, each occurrence of `open` within the comment must be matched by
+    /// its own `close`. Returns an error pointing at the opening delimiter if the comment is
+    /// never closed, in which case the [`ParseStream`] is not advanced.
+    pub fn parse_with(
+        stream: &mut ParseStream,
+        open: impl ToString,
+        close: impl ToString,
+        nested: bool,
+    ) -> Result<Self> {
+        let (open, close) = (open.to_string(), close.to_string());
+        let start_position = stream.position;
+        let open_span = stream.parse_str(&open)?.span();
+        let body_position = stream.position;
+        let mut depth = 1;
+        loop {
+            if stream.peek_str(&close) {
+                let body_end = stream.position;
+                stream.parse_str(&close)?;
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(BlockComment {
+                        span: stream.span_from(start_position),
+                        body: Span::new(stream.source().clone(), body_position..body_end),
+                    });
+                }
+            } else if nested && stream.peek_str(&open) {
+                stream.parse_str(&open)?;
+                depth += 1;
+            } else if stream.parse_char().is_err() {
+                stream.position = start_position;
+                return Err(Error::new(
+                    open_span,
+                    format!("unterminated block comment, expected `{close}`"),
+                ));
+            }
+        }
+    }
+
+    /// Returns the [`Span`] of the text of this comment, excluding the delimiters.
+    pub fn body(&self) -> &Span {
+        &self.body
+    }
+}
+
+impl Parsable for BlockComment {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        BlockComment::parse_with(stream, "/*", "*/", true)
+    }
+}
+
+#[test]
+fn test_parse_line_comment() {
+    let mut stream = ParseStream::from("// hello wörld\nnext");
+    let comment = stream.parse::<LineComment>().unwrap();
+    assert_eq!(comment.span().source_text(), "// hello wörld");
+    assert_eq!(comment.body().source_text(), " hello wörld");
+    assert_eq!(stream.remaining(), "\nnext");
+    let mut stream = ParseStream::from("# windows\r\n");
+    let comment = LineComment::parse_with(&mut stream, "#").unwrap();
+    assert_eq!(comment.body().source_text(), " windows");
+    assert_eq!(stream.remaining(), "\r\n");
+    let mut stream = ParseStream::from("//");
+    assert_eq!(
+        stream.parse::<LineComment>().unwrap().body().source_text(),
+        ""
+    );
+    assert!(ParseStream::from("/ nope").parse::<LineComment>().is_err());
+}
+
+#[test]
+fn test_parse_block_comment() {
+    let mut stream = ParseStream::from("/* a /* b */ c */ d");
+    let comment = stream.parse::<BlockComment>().unwrap();
+    assert_eq!(comment.span().source_text(), "/* a /* b */ c */");
+    assert_eq!(comment.body().source_text(), " a /* b */ c ");
+    assert_eq!(stream.remaining(), " d");
+    let mut stream = ParseStream::from("/* a /* b */ c */");
+    let comment = BlockComment::parse_with(&mut stream, "/*", "*/", false).unwrap();
+    assert_eq!(comment.span().source_text(), "/* a /* b */");
+    let mut stream = ParseStream::from("{- é\n -}");
+    let comment = BlockComment::parse_with(&mut stream, "{-", "-}", true).unwrap();
+    assert_eq!(comment.body().source_text(), " é\n ");
+}
+
+#[test]
+fn test_parse_block_comment_unterminated() {
+    let mut stream = ParseStream::from("x /* a /* b */ c");
+    stream.position = 2;
+    let err = stream.parse::<BlockComment>().unwrap_err();
+    assert_eq!(err.span().byte_range(), &(2..4));
+    assert!(err.message().contains("unterminated block comment"));
+    assert_eq!(stream.position, 2);
+}
@@ -0,0 +1,106 @@
+use super::*;
+
+use crate as quoth;
+
+/// A placeholder for a part of the input that failed to parse, for building a partial syntax
+/// tree with holes in it rather than failing the whole parse.
+///
+/// An [`ErrorNode`] records the [`Span`] of the offending input, and unparses to that input
+/// unchanged. It is typically embedded as a variant of an AST enum and created during error
+/// recovery via [`ErrorNode::new`] or [`ParseStream::error_node`], after the problem has been
+/// [emitted](ParseStream::emit) as a [`Diagnostic`]. Parsing an [`ErrorNode`] directly always
+/// fails, so that it is never chosen in place of valid syntax.
+///
+/// ```
+/// use quoth::{parsable::{numbers::U64, ErrorNode, Whitespace}, *};
+///
+/// let mut stream = ParseStream::from("1 ? 3");
+/// let mut items = Vec::new();
+/// while !stream.remaining().is_empty() {
+///     stream.skip_whitespace();
+///     match stream.parse::<U64>() {
+///         Ok(n) => items.push(Ok(n)),
+///         Err(err) => {
+///             let span = stream.recover_to::<Whitespace>();
+///             items.push(Err(ErrorNode::new(span)));
+///             stream.emit(err.into());
+///         }
+///     }
+/// }
+/// assert_eq!(items.len(), 3);
+/// assert_eq!(items[1].as_ref().unwrap_err().span().source_text(), "?");
+/// assert_eq!(stream.diagnostics().len(), 1);
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct ErrorNode(Span);
+
+impl ErrorNode {
+    /// Creates an [`ErrorNode`] covering the given [`Span`] of invalid input.
+    pub fn new(span: Span) -> ErrorNode {
+        ErrorNode(span)
+    }
+}
+
+/// The default [`ErrorNode`] covers a [`Span::blank`], for use with
+/// [`ParseStream::error_default`].
+impl Default for ErrorNode {
+    fn default() -> Self {
+        ErrorNode(Span::blank())
+    }
+}
+
+impl Parsable for ErrorNode {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        Err(Error::new(stream.current_span(), "expected valid input"))
+    }
+}
+
+#[test]
+fn test_error_node() {
+    use crate::parsable::numbers::U64;
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+    enum Item {
+        Int(U64),
+        Error(ErrorNode),
+    }
+
+    impl Default for Item {
+        fn default() -> Self {
+            Item::Error(ErrorNode::default())
+        }
+    }
+
+    impl Parsable for Item {
+        fn parse(stream: &mut ParseStream) -> Result<Self> {
+            if stream.peek::<U64>() {
+                return stream.parse().map(Item::Int);
+            }
+            let start_position = stream.mark();
+            if stream.recover_to::<U64>().is_blank() {
+                return Ok(stream.error_default(stream.current_span(), "expected more input"));
+            }
+            let span = stream.span_from(start_position);
+            Ok(Item::Error(stream.error_node(span, "expected a number")))
+        }
+    }
+
+    let mut stream = ParseStream::from("1 x 2");
+    let items: Vec<Item> = (0..3).map(|_| stream.parse().unwrap()).collect();
+    assert!(matches!(items[0], Item::Int(_)));
+    assert_eq!(items[1].span().source_text(), " x ");
+    assert_eq!(items[1].to_string(), " x ");
+    assert!(matches!(items[2], Item::Int(_)));
+    let diagnostics = stream.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    let diagnostic = diagnostics.iter().next().unwrap();
+    assert_eq!(diagnostic.message(), "expected a number");
+    assert_eq!(diagnostic.span().source_text(), " x ");
+    let item = stream.parse::<Item>().unwrap();
+    assert_eq!(item, Item::default());
+    assert!(item.span().is_blank());
+    assert_eq!(stream.diagnostics().len(), 2);
+    let err = ParseStream::from("x").parse::<ErrorNode>().unwrap_err();
+    assert_eq!(err.message(), "expected valid input");
+    assert_eq!(ErrorNode::new(Span::blank()).to_string(), "");
+}
@@ -33,10 +33,7 @@ impl Parsable for Exact {
         if stream.remaining().starts_with(&text) {
             let start_position = stream.position;
             stream.position += text.len();
-            return Ok(Exact(Span::new(
-                stream.source().clone(),
-                start_position..stream.position,
-            )));
+            return Ok(Exact(stream.span_from(start_position)));
         }
         let prefix = common_prefix(&text, stream.remaining());
         stream.consume(prefix.len())?;
@@ -46,6 +43,41 @@ impl Parsable for Exact {
     }
 }
 
+/// A case-insensitive version of [`Exact`], matching its text regardless of case.
+///
+/// The [`Span`] of a parsed [`IExact`] points at the input as written, so parsing
+/// `IExact::from("select")` from `SELECT *` yields a [`Span`] whose text is `SELECT`. See
+/// [`ParseStream::parse_istr`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq, ParsableExt, Spanned)]
+pub struct IExact(pub Span);
+
+impl IExact {
+    pub fn new(span: impl Into<Span>) -> Self {
+        IExact(span.into())
+    }
+
+    pub fn from(source: impl Into<Source>) -> Self {
+        IExact(Exact::from(source).0)
+    }
+}
+
+impl From<IExact> for Exact {
+    fn from(value: IExact) -> Self {
+        Exact(value.0)
+    }
+}
+
+impl Parsable for IExact {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        Ok(IExact(stream.span_from(stream.position)))
+    }
+
+    fn parse_value(value: Self, stream: &mut ParseStream) -> Result<Self> {
+        let exact = stream.parse_istr(value.0.source_text())?;
+        Ok(IExact(exact.0))
+    }
+}
+
 #[test]
 fn test_parse_exact() {
     let mut stream = ParseStream::from("hey this is a cool string");
@@ -72,3 +104,24 @@ fn test_parse_exact() {
     let ex = stream.parse_value(Exact::from(".")).unwrap();
     assert_eq!(ex.to_string(), ".");
 }
+
+#[test]
+fn test_parse_iexact() {
+    let mut stream = ParseStream::from("SELECT * FROM t");
+    let parsed = stream.parse_value(IExact::from("select")).unwrap();
+    assert_eq!(parsed.span().source_text(), "SELECT");
+    assert_eq!(parsed.to_string(), "SELECT");
+    assert!(stream.peek_value(IExact::from(" *")));
+    assert!(!stream.peek_value(IExact::from(" from")));
+    stream.position = 9;
+    assert_eq!(
+        stream
+            .parse_value(IExact::from("from"))
+            .unwrap()
+            .to_string(),
+        "FROM"
+    );
+    let err = stream.parse_value(IExact::from(" x")).unwrap_err();
+    assert!(err.to_string().contains("expected `x`"));
+    assert_eq!(stream.parse::<IExact>().unwrap().span().source_text(), "");
+}
@@ -0,0 +1,54 @@
+use super::*;
+
+use crate as quoth;
+
+/// An ASCII identifier: an ASCII letter or `_`, followed by any number of ASCII letters, digits
+/// and `_`s, e.g. `foo_bar2`.
+///
+/// This is intended for performance-sensitive parsing of DSLs that are known to be ASCII. It
+/// scans the UTF-8 bytes of [`ParseStream::remaining`] directly rather than going through
+/// [`ParseStream::peek_char`] one character at a time. Since every byte it accepts is ASCII
+/// (and hence a whole character), the number of bytes matched is also the number of
+/// characters to advance by, so no per-character bookkeeping is needed.
+///
+/// Non-ASCII characters are never part of an [`AsciiIdentifier`], so `abcé` parses as `abc`.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct AsciiIdentifier(Span);
+
+impl Parsable for AsciiIdentifier {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        let remaining = stream.remaining();
+        let bytes = remaining.as_str().as_bytes();
+        let len = match bytes.first() {
+            Some(b) if b.is_ascii_alphabetic() || *b == b'_' => {
+                1 + bytes[1..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+                    .count()
+            }
+            _ => return Err(Error::new(stream.current_span(), "expected identifier")),
+        };
+        let start_position = stream.mark();
+        stream.position += len;
+        Ok(AsciiIdentifier(stream.span_from(start_position)))
+    }
+}
+
+#[test]
+fn test_parse_ascii_identifier() {
+    let mut stream = ParseStream::from("_foo_Bar2 baz");
+    let parsed = stream.parse::<AsciiIdentifier>().unwrap();
+    assert_eq!(parsed.span().source_text(), "_foo_Bar2");
+    assert_eq!(stream.remaining(), " baz");
+    let err = stream.parse::<AsciiIdentifier>().unwrap_err();
+    assert_eq!(err.message(), "expected identifier");
+    assert_eq!(stream.position, 9);
+    let mut stream = ParseStream::from("héllo");
+    stream.position = 1;
+    assert!(stream.parse::<AsciiIdentifier>().is_err());
+    let mut stream = ParseStream::from("abcé d");
+    let parsed = stream.parse::<AsciiIdentifier>().unwrap();
+    assert_eq!(parsed.span().byte_range(), &(0..3));
+    assert_eq!(stream.next_char().unwrap(), 'é');
+    assert!(ParseStream::from("9a").parse::<AsciiIdentifier>().is_err());
+}
@@ -0,0 +1,204 @@
+use std::{fmt::Display, marker::PhantomData, str::FromStr};
+
+use super::*;
+
+/// A list of `key = value` pairs separated by `Sep`, such as the body of a simple config file.
+///
+/// Each entry is a `K`, a `=` and a `V`, with optional spaces and tabs around the `=`. Entries
+/// are separated by a `Sep` (by default a [`Newline`]), optionally preceded by spaces and tabs
+/// and followed by any whitespace, so blank lines between entries are allowed. A trailing
+/// separator is consumed (use [`KeyValues::parse_with`] to forbid or require one), and
+/// parsing stops at the first position where no `K` can be parsed, so an empty list is valid.
+///
+/// Duplicate keys are allowed while parsing; use [`KeyValues::check_duplicate_keys`] to reject
+/// them. Keys are compared by their unparsed text (see [`Parsable::unparse_to_string`]).
+///
+/// ```
+/// use quoth::{
+///     parsable::{numbers::U64, KeyValues},
+///     *,
+/// };
+///
+/// let parsed: KeyValues<U64, U64> = "1 = 10\n2=20\n\n3 = 30".parse().unwrap();
+/// assert_eq!(parsed.len(), 3);
+/// assert_eq!(parsed.get("2").unwrap().value(), 20);
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct KeyValues<K: Parsable, V: Parsable, Sep: Parsable = Newline> {
+    pairs: Vec<(K, V)>,
+    span: Span,
+    _sep: PhantomData<Sep>,
+}
+
+impl<K: Parsable, V: Parsable, Sep: Parsable> KeyValues<K, V, Sep> {
+    /// Returns the parsed pairs, in the order in which they appeared.
+    pub fn pairs(&self) -> &[(K, V)] {
+        &self.pairs
+    }
+
+    /// Consumes this [`KeyValues`], returning the parsed pairs.
+    pub fn into_pairs(self) -> Vec<(K, V)> {
+        self.pairs
+    }
+
+    /// Returns the number of pairs in this [`KeyValues`].
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Returns `true` if this [`KeyValues`] contains no pairs.
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    /// Returns the value of the first pair whose key unparses to `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k.unparse_to_string() == key)
+            .map(|(_, v)| v)
+    }
+
+    /// Returns an error if any key appears more than once.
+    ///
+    /// The error spans the second occurrence of the first duplicated key, with a note pointing
+    /// at its first occurrence.
+    pub fn check_duplicate_keys(&self) -> Result<()> {
+        let mut seen: Vec<(String, Span)> = Vec::new();
+        for (key, _) in &self.pairs {
+            let text = key.unparse_to_string();
+            if let Some((_, first)) = seen.iter().find(|(seen, _)| *seen == text) {
+                return Err(Error::new(key.span(), format!("duplicate key `{text}`"))
+                    .with_note(first.clone(), "first defined here"));
+            }
+            seen.push((text, key.span()));
+        }
+        Ok(())
+    }
+}
+
+impl<K: Parsable, V: Parsable, Sep: Parsable> Spanned for KeyValues<K, V, Sep> {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+impl<K: Parsable, V: Parsable, Sep: Parsable> Display for KeyValues<K, V, Sep> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.unparse(f)
+    }
+}
+
+impl<K: Parsable, V: Parsable, Sep: Parsable> FromStr for KeyValues<K, V, Sep> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+impl<K: Parsable, V: Parsable, Sep: Parsable> KeyValues<K, V, Sep> {
+    /// Parses a [`KeyValues`] from the [`ParseStream`] with the given trailing separator
+    /// policy, see [`Trailing`]. On error the [`ParseStream`] is not advanced.
+    pub fn parse_with(stream: &mut ParseStream, trailing: Trailing) -> Result<Self> {
+        stream.transaction(|stream| {
+            let start_position = stream.mark();
+            let mut pairs = Vec::new();
+            let mut dangling = None;
+            loop {
+                let Ok(key) = stream.transaction(|stream| stream.parse::<K>()) else {
+                    if let Some(sep) = dangling {
+                        trailing.check_dangling(sep)?;
+                    }
+                    break;
+                };
+                skip_spaces(stream);
+                stream.parse_exact_char('=')?;
+                skip_spaces(stream);
+                let value = stream.parse::<V>()?;
+                pairs.push((key, value));
+                let end_position = stream.mark();
+                skip_spaces(stream);
+                let Ok(sep) = stream.transaction(|stream| stream.parse::<Sep>()) else {
+                    stream.position = end_position;
+                    trailing.check_missing(stream)?;
+                    break;
+                };
+                dangling = Some(sep.span());
+                stream.skip_whitespace();
+            }
+            Ok(KeyValues {
+                pairs,
+                span: stream.span_from(start_position),
+                _sep: PhantomData,
+            })
+        })
+    }
+}
+
+impl<K: Parsable, V: Parsable, Sep: Parsable> Parsable for KeyValues<K, V, Sep> {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        KeyValues::parse_with(stream, Trailing::default())
+    }
+}
+
+/// Consumes any spaces and tabs at the current position.
+fn skip_spaces(stream: &mut ParseStream) {
+    stream.recover_while(|stream| matches!(stream.peek_char(), Some(' ' | '\t')));
+}
+
+#[test]
+fn test_parse_key_values() {
+    use crate::parsable::numbers::U64;
+    let mut stream = ParseStream::from("1 = 10\n2=20 \n\n  3\t=  30\n\n!");
+    let parsed = stream.parse::<KeyValues<U64, U64>>().unwrap();
+    let pairs: Vec<_> = parsed
+        .pairs()
+        .iter()
+        .map(|(k, v)| (k.value(), v.value()))
+        .collect();
+    assert_eq!(pairs, [(1, 10), (2, 20), (3, 30)]);
+    assert_eq!(parsed.get("2").unwrap().value(), 20);
+    assert_eq!(parsed.get("4"), None);
+    assert_eq!(stream.remaining(), "!");
+    assert!(parsed.check_duplicate_keys().is_ok());
+    let parsed = ParseStream::from("1 = 10 x")
+        .parse::<KeyValues<U64, U64>>()
+        .unwrap();
+    assert_eq!(parsed.span().source_text(), "1 = 10");
+    let parsed = ParseStream::from("x").parse::<KeyValues<U64, U64>>();
+    assert!(parsed.unwrap().is_empty());
+}
+
+#[test]
+fn test_parse_key_values_errors() {
+    use crate::parsable::numbers::U64;
+    let mut stream = ParseStream::from("1 = 10\n2 = x");
+    let err = stream.parse::<KeyValues<U64, U64>>().unwrap_err();
+    assert_eq!(err.span().source_text(), "x");
+    assert_eq!(stream.position, 0);
+    let parsed = ParseStream::from("1 = 10\n2 = 20\n1 = 30")
+        .parse::<KeyValues<U64, U64>>()
+        .unwrap();
+    let err = parsed.check_duplicate_keys().unwrap_err();
+    assert_eq!(err.message(), "duplicate key `1`");
+    assert_eq!(err.span().byte_range(), &(14..15));
+    assert_eq!(err.children()[0].span().byte_range(), &(0..1));
+    assert_eq!(err.children()[0].message(), "first defined here");
+}
+
+#[test]
+fn test_parse_key_values_trailing() {
+    use crate::parsable::numbers::U64;
+    let mut stream = ParseStream::from("1 = 10\n2 = 20\n");
+    let err = KeyValues::<U64, U64>::parse_with(&mut stream, Trailing::Forbid).unwrap_err();
+    assert_eq!(err.message(), "trailing separator is not allowed");
+    assert_eq!(err.span().byte_range(), &(13..14));
+    assert_eq!(stream.position, 0);
+    let parsed = KeyValues::<U64, U64>::parse_with(&mut stream, Trailing::Require).unwrap();
+    assert_eq!(parsed.len(), 2);
+    let mut stream = ParseStream::from("1 = 10\n2 = 20");
+    let err = KeyValues::<U64, U64>::parse_with(&mut stream, Trailing::Require).unwrap_err();
+    assert_eq!(err.message(), "expected trailing separator");
+    assert!(KeyValues::<U64, U64>::parse_with(&mut stream, Trailing::Forbid).is_ok());
+}
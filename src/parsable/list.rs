@@ -0,0 +1,615 @@
+use std::{fmt::Display, ops::Deref, str::FromStr};
+
+use super::*;
+
+/// Zero or more consecutive `T`s, i.e. the `X*` of a BNF grammar.
+///
+/// Parsing is greedy: `T`s are parsed until the next one fails to parse, or until a `T`
+/// consumes no input, so a [`List`] never fails to parse and never loops forever. Any
+/// separators or whitespace between items must be handled by `T` itself.
+///
+/// The span of a [`List`] is the join of the spans of its items, or [`Span::blank`] if it is
+/// empty.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct List<T: Parsable>(Vec<T>);
+
+impl<T: Parsable> List<T> {
+    /// Consumes this [`List`], returning the parsed items.
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T: Parsable> From<Vec<T>> for List<T> {
+    fn from(value: Vec<T>) -> Self {
+        List(value)
+    }
+}
+
+impl<T: Parsable> From<List<T>> for Vec<T> {
+    fn from(value: List<T>) -> Self {
+        value.0
+    }
+}
+
+impl<T: Parsable> Deref for List<T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Parsable> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T: Parsable> IntoIterator for &'a List<T> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<T: Parsable> Spanned for List<T> {
+    fn span(&self) -> Span {
+        Span::union_all(self.0.iter().map(Spanned::span))
+            .ok()
+            .flatten()
+            .unwrap_or_else(Span::blank)
+    }
+}
+
+impl<T: Parsable> Display for List<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.iter().try_for_each(|item| write!(f, "{item}"))
+    }
+}
+
+impl<T: Parsable> FromStr for List<T> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+impl<T: Parsable> Parsable for List<T> {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        let mut items = Vec::new();
+        loop {
+            let position = stream.position;
+            let Ok(item) = stream.transaction(|stream| stream.parse::<T>()) else {
+                break;
+            };
+            items.push(item);
+            if stream.position == position {
+                break;
+            }
+        }
+        Ok(List(items))
+    }
+
+    fn parse_value(value: Self, stream: &mut ParseStream) -> Result<Self> {
+        stream.transaction(|stream| {
+            value
+                .0
+                .into_iter()
+                .map(|item| stream.parse_value(item))
+                .collect::<Result<_>>()
+                .map(List)
+        })
+    }
+
+    fn unparse(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.iter().try_for_each(|item| item.unparse(f))
+    }
+}
+
+/// Whether a separated list may, or must, end with a separator, see
+/// [`Separated::parse_with`] and [`KeyValues::parse_with`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default)]
+pub enum Trailing {
+    /// A separator after the last item is an error, pointing at the separator.
+    Forbid,
+    /// A separator after the last item is consumed if present.
+    #[default]
+    Allow,
+    /// A non-empty list must end with a separator, and it is an error if the last item isn't
+    /// followed by one.
+    Require,
+}
+
+/// Zero or more `T`s separated by `Sep`s, such as the `1,2,3` of a comma-separated list.
+///
+/// By default a trailing separator is allowed (see [`Trailing`]); use
+/// [`Separated::parse_with`] to forbid or require one. As with [`List`], any whitespace
+/// between items and separators must be handled by `T` and `Sep` themselves.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Separated<T: Parsable, Sep: Parsable> {
+    items: Vec<T>,
+    separators: Vec<Sep>,
+    span: Span,
+}
+
+impl<T: Parsable, Sep: Parsable> Separated<T, Sep> {
+    /// Parses a [`Separated`] from the [`ParseStream`] with the given trailing separator
+    /// policy.
+    ///
+    /// Items are parsed for as long as each separator is followed by another `T`, stopping if
+    /// an item and its separator consume no input, so parsing never loops forever. On error
+    /// the [`ParseStream`] is not advanced.
+    pub fn parse_with(stream: &mut ParseStream, trailing: Trailing) -> Result<Self> {
+        stream.transaction(|stream| {
+            let start_position = stream.mark();
+            let mut items = Vec::new();
+            let mut separators = Vec::new();
+            let mut dangling = None;
+            loop {
+                let position = stream.position;
+                let Ok(item) = stream.transaction(|stream| stream.parse::<T>()) else {
+                    if let Some(sep_span) = dangling {
+                        trailing.check_dangling(sep_span)?;
+                    }
+                    break;
+                };
+                items.push(item);
+                let Ok(sep) = stream.transaction(|stream| stream.parse::<Sep>()) else {
+                    trailing.check_missing(stream)?;
+                    break;
+                };
+                dangling = Some(sep.span());
+                separators.push(sep);
+                if stream.position == position {
+                    break;
+                }
+            }
+            Ok(Separated {
+                items,
+                separators,
+                span: stream.span_from(start_position),
+            })
+        })
+    }
+
+    /// Returns the parsed items, in order.
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Returns the parsed separators, in order, including any trailing separator.
+    pub fn separators(&self) -> &[Sep] {
+        &self.separators
+    }
+
+    /// Returns `true` if the last item is followed by a separator.
+    pub fn has_trailing(&self) -> bool {
+        !self.items.is_empty() && self.separators.len() == self.items.len()
+    }
+
+    /// Consumes this [`Separated`], returning the parsed items.
+    pub fn into_items(self) -> Vec<T> {
+        self.items
+    }
+}
+
+impl Trailing {
+    /// Checks whether a non-empty list of items that ended without a trailing separator is
+    /// acceptable.
+    pub(crate) fn check_missing(self, stream: &ParseStream) -> Result<()> {
+        if self == Trailing::Require {
+            return Err(Error::new(
+                stream.current_span(),
+                "expected trailing separator",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks whether a trailing separator with the given span is acceptable.
+    pub(crate) fn check_dangling(self, separator: Span) -> Result<()> {
+        if self == Trailing::Forbid {
+            return Err(Error::new(separator, "trailing separator is not allowed"));
+        }
+        Ok(())
+    }
+}
+
+impl<T: Parsable, Sep: Parsable> Spanned for Separated<T, Sep> {
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+impl<T: Parsable, Sep: Parsable> Display for Separated<T, Sep> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.unparse(f)
+    }
+}
+
+impl<T: Parsable, Sep: Parsable> FromStr for Separated<T, Sep> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+impl<T: Parsable, Sep: Parsable> Parsable for Separated<T, Sep> {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        Separated::parse_with(stream, Trailing::default())
+    }
+}
+
+/// An `Open` delimiter, followed by `T`s separated by `Sep`s, followed by a `Close` delimiter,
+/// such as `[a, b, c]` or `(x; y; z)`.
+///
+/// The list between the delimiters is a [`Separated`] and may be empty, so `[]` is valid. If
+/// the `Close` delimiter is missing, the error points at the `Open` delimiter, with the reason
+/// the `Close` delimiter could not be parsed as a child. As with [`Separated`], any whitespace
+/// around the delimiters must be handled by `Open`, `T`, `Sep` and `Close` themselves.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Delimited<Open: Parsable, T: Parsable, Sep: Parsable, Close: Parsable> {
+    open: Open,
+    list: Separated<T, Sep>,
+    close: Close,
+    span: Span,
+}
+
+impl<Open: Parsable, T: Parsable, Sep: Parsable, Close: Parsable> Delimited<Open, T, Sep, Close> {
+    /// Parses a [`Delimited`] from the [`ParseStream`] with the given trailing separator
+    /// policy for the list between the delimiters, see [`Trailing`].
+    ///
+    /// On error the [`ParseStream`] is not advanced.
+    pub fn parse_with(stream: &mut ParseStream, trailing: Trailing) -> Result<Self> {
+        stream.transaction(|stream| {
+            let start_position = stream.mark();
+            let open = stream.parse::<Open>()?;
+            let list = Separated::parse_with(stream, trailing)?;
+            let close = stream.parse::<Close>().map_err(|err| {
+                Error::new(open.span(), "unclosed delimiter").with_child(err.into())
+            })?;
+            Ok(Delimited {
+                open,
+                list,
+                close,
+                span: stream.span_from(start_position),
+            })
+        })
+    }
+
+    /// Returns the opening delimiter.
+    pub fn open(&self) -> &Open {
+        &self.open
+    }
+
+    /// Returns the list between the delimiters.
+    pub fn list(&self) -> &Separated<T, Sep> {
+        &self.list
+    }
+
+    /// Returns the parsed items between the delimiters, in order.
+    pub fn items(&self) -> &[T] {
+        self.list.items()
+    }
+
+    /// Returns the closing delimiter.
+    pub fn close(&self) -> &Close {
+        &self.close
+    }
+
+    /// Consumes this [`Delimited`], returning the parsed items.
+    pub fn into_items(self) -> Vec<T> {
+        self.list.into_items()
+    }
+}
+
+impl<Open: Parsable, T: Parsable, Sep: Parsable, Close: Parsable> Spanned
+    for Delimited<Open, T, Sep, Close>
+{
+    fn span(&self) -> Span {
+        self.span.clone()
+    }
+}
+
+impl<Open: Parsable, T: Parsable, Sep: Parsable, Close: Parsable> Display
+    for Delimited<Open, T, Sep, Close>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.unparse(f)
+    }
+}
+
+impl<Open: Parsable, T: Parsable, Sep: Parsable, Close: Parsable> FromStr
+    for Delimited<Open, T, Sep, Close>
+{
+    type Err = Error;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+impl<Open: Parsable, T: Parsable, Sep: Parsable, Close: Parsable> Parsable
+    for Delimited<Open, T, Sep, Close>
+{
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        Delimited::parse_with(stream, Trailing::default())
+    }
+}
+
+#[test]
+fn test_parse_list() {
+    use crate::parsable::numbers::U64;
+    let mut stream = ParseStream::from("/* a *//* b */\n/* c */ rest");
+    let parsed = stream.parse::<List<BlockComment>>().unwrap();
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(parsed.span().source_text(), "/* a *//* b */");
+    assert_eq!(parsed.to_string(), "/* a *//* b */");
+    assert_eq!(stream.remaining(), "\n/* c */ rest");
+    let mut stream = ParseStream::from("\n\r\n\nx");
+    let parsed = stream.parse::<List<Newline>>().unwrap();
+    assert_eq!(parsed.into_inner().len(), 3);
+    let mut stream = ParseStream::from("x");
+    let parsed = stream.parse::<List<U64>>().unwrap();
+    assert!(parsed.is_empty());
+    assert!(parsed.span().is_blank());
+    assert_eq!(stream.position, 0);
+    let mut stream = ParseStream::from("abc");
+    let parsed = stream.parse::<List<Optional<U64>>>().unwrap();
+    assert_eq!(parsed.len(), 1);
+    assert_eq!(stream.position, 0);
+}
+
+#[test]
+fn test_parse_list_nested() {
+    use crate as quoth;
+    use crate::parsable::numbers::U64;
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+    struct Open(Span);
+
+    impl Parsable for Open {
+        fn parse(stream: &mut ParseStream) -> Result<Self> {
+            Ok(Open(stream.parse_exact_char('(')?))
+        }
+    }
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+    struct Close(Span);
+
+    impl Parsable for Close {
+        fn parse(stream: &mut ParseStream) -> Result<Self> {
+            Ok(Close(stream.parse_exact_char(')')?))
+        }
+    }
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned, Parsable)]
+    enum Item {
+        Group(Box<Group>),
+        Int(U64),
+    }
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned, Parsable)]
+    struct Group {
+        open: Open,
+        items: List<Item>,
+        close: Close,
+        span: Span,
+    }
+
+    // peeking before each item used to parse every level twice, which is exponential in depth
+    let depth = 40;
+    let input = format!("{}1{} rest", "(".repeat(depth), ")".repeat(depth));
+    let mut stream = ParseStream::from(input.as_str());
+    let parsed = stream.parse::<List<Item>>().unwrap();
+    assert_eq!(parsed.len(), 1);
+    assert_eq!(stream.remaining(), " rest");
+    let mut depth_found = 0;
+    let mut item = &parsed[0];
+    while let Item::Group(group) = item {
+        depth_found += 1;
+        item = &group.items[0];
+    }
+    assert_eq!(depth_found, depth);
+
+    let mut stream = ParseStream::from("(1(2");
+    let parsed = stream.parse::<List<Item>>().unwrap();
+    assert!(parsed.is_empty());
+    assert_eq!(stream.position, 0);
+}
+
+#[test]
+fn test_parse_separated() {
+    use crate as quoth;
+    use crate::parsable::numbers::U64;
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+    struct Comma(Span);
+
+    impl Parsable for Comma {
+        fn parse(stream: &mut ParseStream) -> Result<Self> {
+            Ok(Comma(stream.parse_exact_char(',')?))
+        }
+    }
+
+    let mut stream = ParseStream::from("1,2,3 rest");
+    let parsed = stream.parse::<Separated<U64, Comma>>().unwrap();
+    let items: Vec<_> = parsed.items().iter().map(U64::value).collect();
+    assert_eq!(items, [1, 2, 3]);
+    assert_eq!(parsed.separators().len(), 2);
+    assert!(!parsed.has_trailing());
+    assert_eq!(parsed.span().source_text(), "1,2,3");
+    let parsed = ParseStream::from("1,2,")
+        .parse::<Separated<U64, Comma>>()
+        .unwrap();
+    assert!(parsed.has_trailing());
+    assert_eq!(parsed.into_items().len(), 2);
+    let parsed = ParseStream::from("x").parse::<Separated<U64, Comma>>();
+    assert!(parsed.unwrap().items().is_empty());
+
+    let parse = |input, trailing| {
+        let mut stream = ParseStream::from(input);
+        let result = Separated::<U64, Comma>::parse_with(&mut stream, trailing);
+        (result, stream.position)
+    };
+    let (result, position) = parse("1,2, x", Trailing::Forbid);
+    let err = result.unwrap_err();
+    assert_eq!(err.message(), "trailing separator is not allowed");
+    assert_eq!(err.span().byte_range(), &(3..4));
+    assert_eq!(position, 0);
+    assert!(parse("1,2 x", Trailing::Forbid).0.is_ok());
+    let (result, position) = parse("1,2 x", Trailing::Require);
+    let err = result.unwrap_err();
+    assert_eq!(err.message(), "expected trailing separator");
+    assert_eq!(err.span().byte_range(), &(3..4));
+    assert_eq!(position, 0);
+    assert!(parse("1,2, x", Trailing::Require).0.unwrap().has_trailing());
+    assert!(parse("x", Trailing::Require).0.is_ok());
+
+    let mut stream = ParseStream::from("x");
+    let parsed = stream
+        .parse::<Separated<Optional<Whitespace>, Optional<Whitespace>>>()
+        .unwrap();
+    assert_eq!(parsed.items().len(), 1);
+    assert_eq!(stream.position, 0);
+}
+
+#[test]
+fn test_parse_separated_nested() {
+    use crate as quoth;
+    use crate::parsable::numbers::U64;
+
+    macro_rules! punct {
+        ($name:ident, $c:literal) => {
+            #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+            struct $name(Span);
+
+            impl Parsable for $name {
+                fn parse(stream: &mut ParseStream) -> Result<Self> {
+                    Ok($name(stream.parse_exact_char($c)?))
+                }
+            }
+        };
+    }
+
+    punct!(Open, '(');
+    punct!(Close, ')');
+    punct!(Comma, ',');
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned, Parsable)]
+    enum Item {
+        Group(Box<Group>),
+        Int(U64),
+    }
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned, Parsable)]
+    struct Group {
+        open: Open,
+        items: Separated<Item, Comma>,
+        close: Close,
+        span: Span,
+    }
+
+    // each level used to parse its items twice, which took minutes at this depth
+    let depth = 40;
+    let input = format!("{}1,2{}", "(".repeat(depth), ")".repeat(depth));
+    let mut stream = ParseStream::from(input.as_str());
+    let parsed = stream.parse::<Separated<Item, Comma>>().unwrap();
+    assert_eq!(parsed.items().len(), 1);
+    assert_eq!(parsed.span().source_text(), input.as_str());
+    let mut depth_found = 0;
+    let mut item = &parsed.items()[0];
+    while let Item::Group(group) = item {
+        depth_found += 1;
+        item = &group.items.items()[0];
+    }
+    assert_eq!(depth_found, depth);
+}
+
+#[test]
+fn test_parse_delimited() {
+    use crate as quoth;
+    use crate::parsable::numbers::U64;
+
+    macro_rules! punct {
+        ($name:ident, $c:literal) => {
+            #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+            struct $name(Span);
+
+            impl Parsable for $name {
+                fn parse(stream: &mut ParseStream) -> Result<Self> {
+                    Ok($name(stream.parse_exact_char($c)?))
+                }
+            }
+        };
+    }
+
+    punct!(Open, '[');
+    punct!(Close, ']');
+    punct!(Comma, ',');
+
+    type Brackets = Delimited<Open, U64, Comma, Close>;
+
+    let mut stream = ParseStream::from("[1,2,3] rest");
+    let parsed = stream
+        .parse_delimited_list::<Open, U64, Comma, Close>()
+        .unwrap();
+    let items: Vec<_> = parsed.items().iter().map(U64::value).collect();
+    assert_eq!(items, [1, 2, 3]);
+    assert_eq!(parsed.span().source_text(), "[1,2,3]");
+    assert_eq!(parsed.close().span().byte_range(), &(6..7));
+    assert_eq!(stream.remaining(), " rest");
+    let parsed = ParseStream::from("[]").parse::<Brackets>().unwrap();
+    assert!(parsed.items().is_empty());
+    assert_eq!(parsed.to_string(), "[]");
+    let parsed = ParseStream::from("[1,]").parse::<Brackets>().unwrap();
+    assert!(parsed.list().has_trailing());
+    assert_eq!(parsed.into_items().len(), 1);
+
+    let mut stream = ParseStream::from("x [1,2");
+    stream.position = 2;
+    let err = stream.parse::<Brackets>().unwrap_err();
+    assert_eq!(err.message(), "unclosed delimiter");
+    assert_eq!(err.span().byte_range(), &(2..3));
+    assert_eq!(err.children()[0].message(), "expected `]`");
+    assert_eq!(err.children()[0].span().byte_range(), &(6..6));
+    assert_eq!(stream.position, 2);
+    let mut stream = ParseStream::from("[1,]");
+    let err = Brackets::parse_with(&mut stream, Trailing::Forbid).unwrap_err();
+    assert_eq!(err.message(), "trailing separator is not allowed");
+    assert!(ParseStream::from("1]").parse::<Brackets>().is_err());
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned, Parsable)]
+    enum Item {
+        Group(Box<Delimited<Open, Item, Comma, Close>>),
+        Int(U64),
+    }
+
+    let depth = 30;
+    let input = format!("{}1,2{}", "[".repeat(depth), "]".repeat(depth));
+    let mut stream = ParseStream::from(input.as_str());
+    let mut item = &stream.parse::<Item>().unwrap();
+    assert!(stream.remaining().is_empty());
+    let mut depth_found = 0;
+    while let Item::Group(group) = item {
+        depth_found += 1;
+        assert_eq!(
+            group.items().len(),
+            if depth_found == depth { 2 } else { 1 }
+        );
+        item = &group.items()[0];
+    }
+    assert_eq!(depth_found, depth);
+    let input = format!("{}1,2{}", "[".repeat(depth), "]".repeat(depth - 1));
+    let mut stream = ParseStream::from(input.as_str());
+    assert!(stream.parse::<Item>().is_err());
+    assert_eq!(stream.position, 0);
+}
@@ -0,0 +1,86 @@
+use std::{fmt::Display, str::FromStr};
+
+use super::*;
+
+/// Parses a `T`, but fails if doing so consumed no input.
+///
+/// Wrapping a parsable that can succeed without consuming anything (such as an [`Optional`])
+/// in [`NonEmpty`] guarantees forward progress, so repeatedly parsing it until it fails can't
+/// loop forever.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct NonEmpty<T: Parsable>(T);
+
+impl<T: Parsable> NonEmpty<T> {
+    /// Returns a reference to the wrapped value.
+    pub fn inner(&self) -> &T {
+        &self.0
+    }
+
+    /// Consumes this [`NonEmpty`], returning the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Parsable> Spanned for NonEmpty<T> {
+    fn span(&self) -> Span {
+        self.0.span()
+    }
+}
+
+impl<T: Parsable> Display for NonEmpty<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<T: Parsable> FromStr for NonEmpty<T> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+impl<T: Parsable> Parsable for NonEmpty<T> {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        let start_position = stream.mark();
+        let value = stream.parse::<T>()?;
+        if stream.position <= start_position {
+            stream.position = start_position;
+            return Err(Error::new(
+                stream.current_span(),
+                "expected a non-empty match",
+            ));
+        }
+        Ok(NonEmpty(value))
+    }
+
+    fn parse_value(value: Self, stream: &mut ParseStream) -> Result<Self> {
+        stream.parse_value(value.0).map(NonEmpty)
+    }
+
+    fn unparse(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.unparse(f)
+    }
+}
+
+#[test]
+fn test_parse_non_empty() {
+    let mut stream = ParseStream::from("  x");
+    let parsed = stream.parse::<NonEmpty<Optional<Whitespace>>>().unwrap();
+    assert_eq!(parsed.span().source_text(), "  ");
+    assert!(parsed.inner().is_some());
+    let err = stream
+        .parse::<NonEmpty<Optional<Whitespace>>>()
+        .unwrap_err();
+    assert_eq!(err.message(), "expected a non-empty match");
+    assert_eq!(err.span().source_text(), "x");
+    assert_eq!(stream.position, 2);
+    let mut count = 0;
+    let mut stream = ParseStream::from(" \t\n");
+    while stream.parse::<NonEmpty<Optional<SpaceOrTab>>>().is_ok() {
+        count += 1;
+    }
+    assert_eq!(count, 1);
+}
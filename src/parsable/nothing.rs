@@ -29,6 +29,37 @@ impl Parsable for Nothing {
     }
 }
 
+/// Matches the end of the input, producing a zero-length [`Span`] there.
+///
+/// Behaves like [`Nothing`], but with a clearer name and error message for asserting that a
+/// grammar has consumed the entire input. See also [`ParseStream::expect_end`] and
+/// [`parse_all`], which additionally allow trailing whitespace.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, ParsableExt, Spanned)]
+pub struct Eoi(Span);
+
+impl Parsable for Eoi {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        if stream.position < stream.source().len() {
+            return Err(Error::new(
+                stream.current_span(),
+                format!(
+                    "expected end of input, found `{}`",
+                    stream.current_span().source_text()
+                ),
+            ));
+        }
+        Ok(Eoi(stream.current_span()))
+    }
+
+    fn parse_value(_value: Self, stream: &mut ParseStream) -> Result<Self> {
+        stream.parse()
+    }
+
+    fn unparse(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Ok(())
+    }
+}
+
 #[test]
 fn test_parse_nothing() {
     let mut stream = ParseStream::from("");
@@ -49,3 +80,13 @@ fn test_parse_value_nothing() {
         .parse_value(Nothing(Span::new(Rc::new(Source::from_str("")), 0..0)))
         .is_err());
 }
+
+#[test]
+fn test_parse_eoi() {
+    let mut stream = ParseStream::from("ab");
+    let err = stream.parse::<Eoi>().unwrap_err();
+    assert_eq!(err.message(), "expected end of input, found `a`");
+    stream.position = 2;
+    let eoi = stream.parse::<Eoi>().unwrap();
+    assert_eq!(eoi.span().byte_range(), &(2..2));
+}
@@ -18,8 +18,8 @@ impl Parsable for U64 {
     fn parse(stream: &mut ParseStream) -> Result<Self> {
         let mut digits = Vec::new();
         let start_position = stream.position;
-        while let Ok(_) = stream.next_digit() {
-            digits.push(stream.parse_digit()?);
+        while stream.next_digit().is_ok() {
+            digits.push(char::from(b'0' + stream.parse_digit()?));
         }
         if digits.is_empty() {
             return Err(Error::new(stream.current_span(), "expected digit"));
@@ -32,12 +32,12 @@ impl Parsable for U64 {
             Ok(val) => val,
             Err(err) => {
                 return Err(Error::new(
-                    Span::new(stream.source().clone(), start_position..stream.position),
+                    stream.span_from(start_position),
                     err.to_string(),
                 ))
             }
         };
-        let span = Span::new(stream.source().clone(), start_position..stream.position);
+        let span = stream.span_from(start_position);
         Ok(U64(parsed, span))
     }
 }
@@ -73,8 +73,8 @@ impl Parsable for U128 {
     fn parse(stream: &mut ParseStream) -> Result<Self> {
         let mut digits = Vec::new();
         let start_position = stream.position;
-        while let Ok(_) = stream.next_digit() {
-            digits.push(stream.parse_digit()?);
+        while stream.next_digit().is_ok() {
+            digits.push(char::from(b'0' + stream.parse_digit()?));
         }
         if digits.is_empty() {
             return Err(Error::new(stream.current_span(), "expected digit"));
@@ -87,12 +87,12 @@ impl Parsable for U128 {
             Ok(val) => val,
             Err(err) => {
                 return Err(Error::new(
-                    Span::new(stream.source().clone(), start_position..stream.position),
+                    stream.span_from(start_position),
                     err.to_string(),
                 ))
             }
         };
-        let span = Span::new(stream.source().clone(), start_position..stream.position);
+        let span = stream.span_from(start_position);
         Ok(U128(parsed, span))
     }
 }
@@ -102,6 +102,30 @@ impl From<U128> for u128 {
         value.0
     }
 }
+/// How the sign of a signed number was written in the source text.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Sign {
+    /// The number was written with a leading `+`.
+    Positive,
+    /// The number was written with a leading `-`.
+    Negative,
+    /// The number was written without a sign.
+    Implicit,
+}
+
+impl Sign {
+    fn of(span: &Span) -> Sign {
+        match span.source_text().chars().first() {
+            Some('+') => Sign::Positive,
+            Some('-') => Sign::Negative,
+            _ => Sign::Implicit,
+        }
+    }
+}
+
+/// A signed 64-bit integer, optionally written with a leading `+` or `-`.
+///
+/// The sign is included in the [`Span`], and [`I64::sign`] reports whether it was written.
 #[derive(Clone, PartialEq, Eq, Hash, Debug, ParsableExt, Spanned)]
 pub struct I64(i64, Span);
 
@@ -109,38 +133,46 @@ impl I64 {
     pub fn value(&self) -> i64 {
         self.0
     }
+
+    /// Returns how the sign of this [`I64`] was written, see [`Sign`].
+    pub fn sign(&self) -> Sign {
+        Sign::of(&self.1)
+    }
 }
 
 impl Parsable for I64 {
     fn parse(stream: &mut ParseStream) -> Result<Self> {
-        let mut digits = Vec::new();
+        // the sign is kept in the buffer so that the most negative value doesn't overflow
+        let mut digits = String::new();
         let start_position = stream.position;
-        let mut sign = 1;
-        if stream.next_char()? == '-' {
-            stream.consume(1)?;
-            sign = -1;
+        match stream.next_char()? {
+            '-' => {
+                stream.consume(1)?;
+                digits.push('-');
+            }
+            '+' => {
+                stream.consume(1)?;
+                digits.push('+');
+            }
+            _ => {}
         }
-        while let Ok(_) = stream.next_digit() {
-            digits.push(stream.parse_digit()?);
+        while stream.next_digit().is_ok() {
+            digits.push(char::from(b'0' + stream.parse_digit()?));
         }
-        if digits.is_empty() {
+        if digits.is_empty() || digits == "-" || digits == "+" {
             return Err(Error::new(stream.current_span(), "expected digit"));
         }
-        let digits = digits
-            .into_iter()
-            .map(|d| d.to_string())
-            .collect::<String>();
         let parsed: i64 = match digits.parse() {
             Ok(val) => val,
             Err(err) => {
                 return Err(Error::new(
-                    Span::new(stream.source().clone(), start_position..stream.position),
+                    stream.span_from(start_position),
                     err.to_string(),
                 ))
             }
         };
-        let span = Span::new(stream.source().clone(), start_position..stream.position);
-        Ok(I64(parsed * sign, span))
+        let span = stream.span_from(start_position);
+        Ok(I64(parsed, span))
     }
 }
 
@@ -156,6 +188,7 @@ impl From<I64> for i128 {
     }
 }
 
+/// A signed 128-bit integer, optionally written with a leading `+` or `-`, see [`I64`].
 #[derive(Clone, PartialEq, Eq, Hash, Debug, ParsableExt, Spanned)]
 pub struct I128(i128, Span);
 
@@ -163,38 +196,46 @@ impl I128 {
     pub fn value(&self) -> i128 {
         self.0
     }
+
+    /// Returns how the sign of this [`I128`] was written, see [`Sign`].
+    pub fn sign(&self) -> Sign {
+        Sign::of(&self.1)
+    }
 }
 
 impl Parsable for I128 {
     fn parse(stream: &mut ParseStream) -> Result<Self> {
-        let mut digits = Vec::new();
+        // the sign is kept in the buffer so that the most negative value doesn't overflow
+        let mut digits = String::new();
         let start_position = stream.position;
-        let mut sign = 1;
-        if stream.next_char()? == '-' {
-            stream.consume(1)?;
-            sign = -1;
+        match stream.next_char()? {
+            '-' => {
+                stream.consume(1)?;
+                digits.push('-');
+            }
+            '+' => {
+                stream.consume(1)?;
+                digits.push('+');
+            }
+            _ => {}
         }
-        while let Ok(_) = stream.next_digit() {
-            digits.push(stream.parse_digit()?);
+        while stream.next_digit().is_ok() {
+            digits.push(char::from(b'0' + stream.parse_digit()?));
         }
-        if digits.is_empty() {
+        if digits.is_empty() || digits == "-" || digits == "+" {
             return Err(Error::new(stream.current_span(), "expected digit"));
         }
-        let digits = digits
-            .into_iter()
-            .map(|d| d.to_string())
-            .collect::<String>();
         let parsed: i128 = match digits.parse() {
             Ok(val) => val,
             Err(err) => {
                 return Err(Error::new(
-                    Span::new(stream.source().clone(), start_position..stream.position),
+                    stream.span_from(start_position),
                     err.to_string(),
                 ))
             }
         };
-        let span = Span::new(stream.source().clone(), start_position..stream.position);
-        Ok(I128(parsed * sign, span))
+        let span = stream.span_from(start_position);
+        Ok(I128(parsed, span))
     }
 }
 
@@ -228,7 +269,7 @@ impl From<rust_decimal::Decimal> for Decimal {
         let st = value.to_string();
         let len = st.len();
         let span = Span::new(Rc::new(Source::from_str(st)), 0..len);
-        Decimal(value, span.into())
+        Decimal(value, span)
     }
 }
 
@@ -239,18 +280,79 @@ impl From<Decimal> for rust_decimal::Decimal {
 }
 
 impl Parsable for Decimal {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        let start_position = stream.position;
+        if stream.next_char()? == '-' {
+            stream.consume(1)?;
+        }
+        let mut has_digits = false;
+        while stream.parse_digit().is_ok() {
+            has_digits = true;
+        }
+        if stream.peek_char() == Some('.') {
+            stream.consume(1)?;
+            while stream.parse_digit().is_ok() {
+                has_digits = true;
+            }
+        }
+        if !has_digits {
+            let err = Error::new(stream.current_span(), "expected digit");
+            stream.position = start_position;
+            return Err(err);
+        }
+        let span = stream.span_from(start_position);
+        let text = span.source_text().to_string();
+        let text = text.strip_suffix('.').unwrap_or(&text);
+        let text = match text.strip_prefix('-') {
+            Some(rest) if rest.starts_with('.') => format!("-0{rest}"),
+            _ if text.starts_with('.') => format!("0{text}"),
+            _ => text.to_string(),
+        };
+        Ok(Decimal(
+            text.parse().map_err(|e| Error::new(span.clone(), e))?,
+            span,
+        ))
+    }
+}
+
+/// A [`Decimal`] that must be written with a `.` and at least one digit on each side of it,
+/// such as `44.0`.
+///
+/// [`Decimal`] itself also accepts `44`, `44.` and `.5`.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct StrictDecimal(rust_decimal::Decimal, Span);
+
+impl StrictDecimal {
+    pub fn value(&self) -> rust_decimal::Decimal {
+        self.0
+    }
+}
+
+impl From<StrictDecimal> for rust_decimal::Decimal {
+    fn from(value: StrictDecimal) -> Self {
+        value.0
+    }
+}
+
+impl From<StrictDecimal> for Decimal {
+    fn from(value: StrictDecimal) -> Self {
+        Decimal(value.0, value.1)
+    }
+}
+
+impl Parsable for StrictDecimal {
     fn parse(stream: &mut ParseStream) -> Result<Self> {
         let start_position = stream.position;
         if stream.next_char()? == '-' {
             stream.consume(1)?;
         }
         stream.parse_digit()?;
-        while let Ok(_) = stream.parse_digit() {}
+        while stream.parse_digit().is_ok() {}
         stream.parse_value(Exact::from("."))?;
         stream.parse_digit()?;
-        while let Ok(_) = stream.parse_digit() {}
-        let span = Span::new(stream.source().clone(), start_position..stream.position);
-        Ok(Decimal(
+        while stream.parse_digit().is_ok() {}
+        let span = stream.span_from(start_position);
+        Ok(StrictDecimal(
             span.source_text()
                 .parse()
                 .map_err(|e| Error::new(span.clone(), e))?,
@@ -259,6 +361,97 @@ impl Parsable for Decimal {
     }
 }
 
+/// A [`Decimal`] written with a configurable group separator and decimal point, such as
+/// `1,234.56` or `1 234,56`.
+///
+/// `GROUP` is the thousands separator and `POINT` is the decimal point, so
+/// [`FormattedDecimal<' ', ','>`] parses `1 234,56`. Grouping is optional, but when present
+/// every group after the first must contain exactly three digits, so `1,23,4` is rejected.
+/// The fractional part is also optional. The [`Span`] covers the text as written, separators
+/// included.
+///
+/// A `GROUP` that is not followed by a digit is not treated as part of the number, so
+/// `1,234, 5` parses `1,234` and leaves `, 5` in the [`ParseStream`].
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct FormattedDecimal<const GROUP: char = ',', const POINT: char = '.'>(
+    rust_decimal::Decimal,
+    Span,
+);
+
+impl<const GROUP: char, const POINT: char> FormattedDecimal<GROUP, POINT> {
+    pub fn value(&self) -> rust_decimal::Decimal {
+        self.0
+    }
+}
+
+impl<const GROUP: char, const POINT: char> From<FormattedDecimal<GROUP, POINT>>
+    for rust_decimal::Decimal
+{
+    fn from(value: FormattedDecimal<GROUP, POINT>) -> Self {
+        value.0
+    }
+}
+
+impl<const GROUP: char, const POINT: char> From<FormattedDecimal<GROUP, POINT>> for Decimal {
+    fn from(value: FormattedDecimal<GROUP, POINT>) -> Self {
+        Decimal(value.0, value.1)
+    }
+}
+
+impl<const GROUP: char, const POINT: char> Parsable for FormattedDecimal<GROUP, POINT> {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        let start_position = stream.position;
+        let mut digits = String::new();
+        if stream.peek_char() == Some('-') {
+            stream.consume(1)?;
+            digits.push('-');
+        }
+        digits.push(char::from(b'0' + stream.parse_digit()?));
+        let mut group_len = 1;
+        while let Ok(digit) = stream.parse_digit() {
+            digits.push(char::from(b'0' + digit));
+            group_len += 1;
+        }
+        while stream.peek_char() == Some(GROUP)
+            && stream.peek_nth(1).is_some_and(|c| c.is_ascii_digit())
+        {
+            let group_start = stream.position;
+            if group_len > 3 {
+                return Err(Error::new(
+                    stream.consume(1)?,
+                    format!("misplaced group separator `{GROUP}`"),
+                ));
+            }
+            stream.consume(1)?;
+            group_len = 0;
+            while let Ok(digit) = stream.parse_digit() {
+                digits.push(char::from(b'0' + digit));
+                group_len += 1;
+            }
+            if group_len != 3 {
+                return Err(Error::new(
+                    stream.span_from(group_start),
+                    "expected a group of 3 digits",
+                ));
+            }
+        }
+        if stream.peek_char() == Some(POINT)
+            && stream.peek_nth(1).is_some_and(|c| c.is_ascii_digit())
+        {
+            stream.consume(1)?;
+            digits.push('.');
+            while let Ok(digit) = stream.parse_digit() {
+                digits.push(char::from(b'0' + digit));
+            }
+        }
+        let span = stream.span_from(start_position);
+        Ok(FormattedDecimal(
+            digits.parse().map_err(|e| Error::new(span.clone(), e))?,
+            span,
+        ))
+    }
+}
+
 /// A bounded version of [`I64`].
 ///
 /// Bounds are _inclusive_, so [`BoundedI64<3, 7>`] means only 3, 4, 5, 6, and 7 are allowed
@@ -336,6 +529,47 @@ fn test_parse_int64() {
     assert!(parsed.to_string().contains("expected digit"));
 }
 
+#[test]
+fn test_parse_explicit_sign() {
+    let mut stream = ParseStream::from("+5 -5 5");
+    let parsed = stream.parse::<I64>().unwrap();
+    assert_eq!(parsed.value(), 5);
+    assert_eq!(parsed.sign(), Sign::Positive);
+    assert_eq!(parsed.to_string(), "+5");
+    stream.position += 1;
+    assert_eq!(stream.parse::<I64>().unwrap().sign(), Sign::Negative);
+    stream.position += 1;
+    assert_eq!(stream.parse::<I64>().unwrap().sign(), Sign::Implicit);
+    let parsed = ParseStream::from("+7").parse::<I128>().unwrap();
+    assert_eq!((parsed.value(), parsed.sign()), (7, Sign::Positive));
+    let err = ParseStream::from("+x").parse::<I64>().unwrap_err();
+    assert!(err.message().contains("expected digit"));
+}
+
+#[test]
+fn test_parse_signed_boundaries() {
+    let parsed = ParseStream::from("-9223372036854775808").parse::<I64>();
+    assert_eq!(parsed.unwrap().value(), i64::MIN);
+    let parsed = ParseStream::from("9223372036854775807").parse::<I64>();
+    assert_eq!(parsed.unwrap().value(), i64::MAX);
+    let err = ParseStream::from("-9223372036854775809")
+        .parse::<I64>()
+        .unwrap_err();
+    assert!(err.message().contains("too small"));
+    let err = ParseStream::from("9223372036854775808")
+        .parse::<I64>()
+        .unwrap_err();
+    assert!(err.message().contains("too large"));
+    let parsed = ParseStream::from(i128::MIN.to_string()).parse::<I128>();
+    assert_eq!(parsed.unwrap().value(), i128::MIN);
+    let parsed = ParseStream::from(i128::MAX.to_string()).parse::<I128>();
+    assert_eq!(parsed.unwrap().value(), i128::MAX);
+    let err = ParseStream::from("-170141183460469231731687303715884105729")
+        .parse::<I128>()
+        .unwrap_err();
+    assert!(err.message().contains("too small"));
+}
+
 #[test]
 fn test_parse_decimal() {
     let mut stream = ParseStream::from("55.63");
@@ -345,13 +579,58 @@ fn test_parse_decimal() {
     let mut stream = ParseStream::from("hey");
     let parsed = stream.parse::<Decimal>().unwrap_err();
     assert!(parsed.to_string().contains("expected digit"));
-    let mut stream = ParseStream::from("44");
-    let parsed = stream.parse::<Decimal>().unwrap_err();
-    assert!(parsed.to_string().contains("expected `.`"));
     let mut stream = ParseStream::from("-24785.24458");
     let parsed = stream.parse::<Decimal>().unwrap();
     assert_eq!(parsed.to_string(), "-24785.24458");
     assert_eq!(parsed.value().to_string(), "-24785.24458");
+    for (input, value) in [("44", "44"), ("44.", "44"), (".5", "0.5"), ("-.5", "-0.5")] {
+        let parsed = ParseStream::from(input).parse::<Decimal>().unwrap();
+        assert_eq!(parsed.to_string(), input);
+        assert_eq!(parsed.value().to_string(), value);
+    }
+    let mut stream = ParseStream::from("-.x");
+    let parsed = stream.parse::<Decimal>().unwrap_err();
+    assert!(parsed.to_string().contains("expected digit"));
+    assert_eq!(stream.position, 0);
+}
+
+#[test]
+fn test_parse_strict_decimal() {
+    let parsed = ParseStream::from("55.63").parse::<StrictDecimal>().unwrap();
+    assert_eq!(parsed.value().to_string(), "55.63");
+    let mut stream = ParseStream::from("44");
+    let parsed = stream.parse::<StrictDecimal>().unwrap_err();
+    assert!(parsed.to_string().contains("expected `.`"));
+    assert!(ParseStream::from(".5").parse::<StrictDecimal>().is_err());
+    assert!(ParseStream::from("44.").parse::<StrictDecimal>().is_err());
+}
+
+#[test]
+fn test_parse_formatted_decimal() {
+    let mut stream = ParseStream::from("-1,234,567.89 rest");
+    let parsed = stream.parse::<FormattedDecimal>().unwrap();
+    assert_eq!(parsed.value().to_string(), "-1234567.89");
+    assert_eq!(parsed.span().source_text(), "-1,234,567.89");
+    assert_eq!(stream.remaining(), " rest");
+    let parsed = ParseStream::from("1 234,5")
+        .parse::<FormattedDecimal<' ', ','>>()
+        .unwrap();
+    assert_eq!(parsed.value().to_string(), "1234.5");
+    assert_eq!(parsed.to_string(), "1 234,5");
+    let mut stream = ParseStream::from("1234, 5");
+    let parsed = stream.parse::<FormattedDecimal>().unwrap();
+    assert_eq!(parsed.value().to_string(), "1234");
+    assert_eq!(stream.remaining(), ", 5");
+    let err = ParseStream::from("1,23,4")
+        .parse::<FormattedDecimal>()
+        .unwrap_err();
+    assert!(err.message().contains("expected a group of 3 digits"));
+    assert_eq!(err.span().source_text(), ",23");
+    let err = ParseStream::from("1234,567")
+        .parse::<FormattedDecimal>()
+        .unwrap_err();
+    assert!(err.message().contains("misplaced group separator `,`"));
+    assert!(ParseStream::from(",5").parse::<FormattedDecimal>().is_err());
 }
 
 #[test]
@@ -0,0 +1,620 @@
+use super::*;
+
+use crate as quoth;
+
+/// A Rust-style raw string literal such as `r"..."`, `r#"..."#` or `r##"..."##`.
+///
+/// The body is not interpreted in any way, so it may contain backslashes, newlines and quotes.
+/// The literal ends at the first `"` followed by as many `#`s as appeared in the opener, so
+/// `r##"a "# b"##` is a single literal whose body is `a "# b`.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct RawString {
+    #[quoth(span)]
+    span: Span,
+    body: Span,
+    hashes: usize,
+}
+
+impl RawString {
+    /// Returns the [`Span`] of the text between the quotes.
+    pub fn body(&self) -> &Span {
+        &self.body
+    }
+
+    /// Returns the number of `#`s used to delimit this [`RawString`].
+    pub fn hashes(&self) -> usize {
+        self.hashes
+    }
+}
+
+impl Parsable for RawString {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        let start_position = stream.mark();
+        stream.parse_str("r")?;
+        let mut hashes = 0;
+        while stream.peek_char() == Some('#') {
+            stream.position += 1;
+            hashes += 1;
+        }
+        if let Err(err) = stream.parse_str("\"") {
+            stream.position = start_position;
+            return Err(err);
+        }
+        let opener = stream.span_from(start_position);
+        let close = format!("\"{}", "#".repeat(hashes));
+        let body_position = stream.mark();
+        loop {
+            if stream.peek_str(&close) {
+                let body = stream.span_from(body_position);
+                stream.position += close.chars().count();
+                return Ok(RawString {
+                    span: stream.span_from(start_position),
+                    body,
+                    hashes,
+                });
+            }
+            if stream.parse_char().is_err() {
+                stream.position = start_position;
+                return Err(Error::new(
+                    opener,
+                    format!("unterminated raw string, expected `{close}`"),
+                ));
+            }
+        }
+    }
+}
+
+/// A run of literal text in a template such as `Hello {name}!`, up to the next unescaped `{`
+/// or the end of the input.
+///
+/// Within the text, `{{` and `}}` are escapes for literal `{` and `}`, so that literal chunks
+/// can be alternated with a parser for `{...}` interpolations. The [`Span`] of a
+/// [`TemplateText`] covers the raw text, while [`TemplateText::text`] returns it decoded.
+///
+/// Parsing fails without consuming anything if the text would be empty, e.g. if the
+/// [`ParseStream`] is already at a `{`. A lone `}`, or a `{` that isn't closed by a `}` before
+/// the next `{`, is also an error.
+///
+/// ```
+/// use quoth::{parsable::TemplateText, *};
+///
+/// let mut stream = ParseStream::from("Hello {{world}}, {name}!");
+/// let parsed = stream.parse::<TemplateText>().unwrap();
+/// assert_eq!(parsed.text(), "Hello {world}, ");
+/// assert_eq!(stream.remaining(), "{name}!");
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct TemplateText {
+    #[quoth(span)]
+    span: Span,
+    text: String,
+}
+
+impl TemplateText {
+    /// Returns the decoded text, with `{{` and `}}` replaced by `{` and `}`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Parsable for TemplateText {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        let start_position = stream.mark();
+        let mut text = String::new();
+        loop {
+            match (stream.peek_char(), stream.peek_nth(1)) {
+                (Some('{'), Some('{')) | (Some('}'), Some('}')) => {
+                    text.push(stream.parse_char()?);
+                    stream.position += 1;
+                }
+                (Some('{'), _) => {
+                    // only look as far as the next brace, so that an unclosed `{` isn't
+                    // matched with the `}` of a later interpolation
+                    let next_brace = stream.remaining().chars()[1..]
+                        .iter()
+                        .find(|c| matches!(c, '{' | '}'))
+                        .copied();
+                    if next_brace != Some('}') {
+                        let err = Error::new(
+                            stream.current_span(),
+                            "unclosed `{`, use `{{` for a literal brace",
+                        );
+                        stream.position = start_position;
+                        return Err(err);
+                    }
+                    break;
+                }
+                (Some('}'), _) => {
+                    let err = Error::new(
+                        stream.current_span(),
+                        "unmatched `}`, use `}}` for a literal brace",
+                    );
+                    stream.position = start_position;
+                    return Err(err);
+                }
+                (Some(c), _) => {
+                    text.push(c);
+                    stream.position += 1;
+                }
+                (None, _) => break,
+            }
+        }
+        if stream.position == start_position {
+            return Err(Error::new(stream.current_span(), "expected template text"));
+        }
+        Ok(TemplateText {
+            span: stream.span_from(start_position),
+            text,
+        })
+    }
+}
+
+/// The escape sequences recognized by [`ParseStream::parse_escape_with`], and hence by
+/// [`QuotedString`] and [`CharLiteral`].
+///
+/// The default recognizes the escapes of Rust string literals: `\n`, `\t`, `\r`, `\\`, `\0`,
+/// `\'` and `\"`, `\xHH` for ASCII characters up to `\x7F`, and `\u{...}` with one to six hex
+/// digits naming any Unicode scalar value.
+///
+/// ```
+/// use quoth::{parsable::Escapes, *};
+///
+/// let escapes = Escapes::default().with_simple('e', '\x1b').unicode(false);
+/// let mut stream = ParseStream::from(r"e u{41}");
+/// assert_eq!(stream.parse_escape_with(&escapes).unwrap(), '\x1b');
+/// stream.position += 1;
+/// assert!(stream.parse_escape_with(&escapes).is_err());
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Escapes {
+    simple: Vec<(char, char)>,
+    hex: bool,
+    unicode: bool,
+}
+
+impl Default for Escapes {
+    fn default() -> Self {
+        Escapes {
+            simple: vec![
+                ('n', '\n'),
+                ('t', '\t'),
+                ('r', '\r'),
+                ('\\', '\\'),
+                ('0', '\0'),
+                ('\'', '\''),
+                ('"', '"'),
+            ],
+            hex: true,
+            unicode: true,
+        }
+    }
+}
+
+impl Escapes {
+    /// Creates [`Escapes`] that recognize no escape sequences at all.
+    pub fn none() -> Escapes {
+        Escapes {
+            simple: Vec::new(),
+            hex: false,
+            unicode: false,
+        }
+    }
+
+    /// Adds a single-character escape, so that a backslash followed by `c` decodes to
+    /// `decoded`, replacing any existing escape for `c`.
+    pub fn with_simple(mut self, c: char, decoded: char) -> Escapes {
+        self = self.without_simple(c);
+        self.simple.push((c, decoded));
+        self
+    }
+
+    /// Removes the single-character escape for `c`, if there is one.
+    pub fn without_simple(mut self, c: char) -> Escapes {
+        self.simple.retain(|(k, _)| *k != c);
+        self
+    }
+
+    /// Sets whether `\xHH` escapes are recognized.
+    pub fn hex(mut self, hex: bool) -> Escapes {
+        self.hex = hex;
+        self
+    }
+
+    /// Sets whether `\u{...}` escapes are recognized.
+    pub fn unicode(mut self, unicode: bool) -> Escapes {
+        self.unicode = unicode;
+        self
+    }
+
+    /// Returns the recognized single-character escapes, as pairs of the character following
+    /// the backslash and the character it decodes to.
+    pub fn simple(&self) -> &[(char, char)] {
+        &self.simple
+    }
+
+    /// Decodes the escape sequence at the current position of `stream`, which is left
+    /// wherever decoding stopped on error.
+    pub(crate) fn decode(&self, stream: &mut ParseStream) -> Result<char> {
+        let Some(c) = stream.peek_char() else {
+            return Err(Error::new(
+                stream.current_span(),
+                "expected escape sequence",
+            ));
+        };
+        if let Some((_, decoded)) = self.simple.iter().find(|(k, _)| *k == c) {
+            stream.position += 1;
+            return Ok(*decoded);
+        }
+        match c {
+            'x' if self.hex => {
+                stream.position += 1;
+                let (value, span) = parse_hex_digits(stream, 2);
+                if span.byte_range().len() != 2 {
+                    return Err(Error::new(
+                        stream.current_span(),
+                        "expected 2 hex digits in `\\x` escape",
+                    ));
+                }
+                if value > 0x7f {
+                    return Err(Error::new(
+                        span,
+                        "out of range hex escape, must be at most `\\x7F`",
+                    ));
+                }
+                Ok(value as u8 as char)
+            }
+            'u' if self.unicode => {
+                stream.position += 1;
+                if stream.parse_exact_char('{').is_err() {
+                    return Err(Error::new(
+                        stream.current_span(),
+                        "expected `{` after `\\u`",
+                    ));
+                }
+                let (value, span) = parse_hex_digits(stream, 7);
+                if span.byte_range().is_empty() {
+                    return Err(Error::new(
+                        stream.current_span(),
+                        "expected hex digits in `\\u{...}` escape",
+                    ));
+                }
+                if span.byte_range().len() > 6 {
+                    return Err(Error::new(
+                        span,
+                        "overlong unicode escape, expected at most 6 hex digits",
+                    ));
+                }
+                if stream.parse_exact_char('}').is_err() {
+                    return Err(Error::new(
+                        stream.current_span(),
+                        "expected `}` to close `\\u{...}` escape",
+                    ));
+                }
+                char::from_u32(value).ok_or_else(|| {
+                    Error::new(
+                        span.clone(),
+                        format!(
+                            "invalid unicode escape, `{}` is not a character",
+                            span.source_text()
+                        ),
+                    )
+                })
+            }
+            _ => Err(Error::new(
+                stream.current_span(),
+                format!("unknown escape sequence `\\{c}`"),
+            )),
+        }
+    }
+}
+
+/// Consumes up to `max` hex digits, returning their value and [`Span`].
+fn parse_hex_digits(stream: &mut ParseStream, max: usize) -> (u32, Span) {
+    let start_position = stream.mark();
+    let mut value: u32 = 0;
+    while stream.position - start_position < max {
+        let Some(digit) = stream.peek_char().and_then(|c| c.to_digit(16)) else {
+            break;
+        };
+        value = value.saturating_mul(16).saturating_add(digit);
+        stream.position += 1;
+    }
+    (value, stream.span_from(start_position))
+}
+
+/// A double-quoted string literal such as `"a\tb"`, in which escape sequences are decoded as
+/// by [`ParseStream::parse_escape`].
+///
+/// The literal may span multiple lines. Its [`Span`] covers the quotes, [`QuotedString::body`]
+/// the raw text between them, and [`QuotedString::value`] returns the decoded text. Use
+/// [`QuotedString::parse_with`] to recognize different [`Escapes`].
+///
+/// ```
+/// use quoth::{parsable::QuotedString, *};
+///
+/// let parsed: QuotedString = r#""tab\there \u{2764}" rest"#.parse().unwrap();
+/// assert_eq!(parsed.value(), "tab\there ❤");
+/// assert_eq!(parsed.body().source_text(), r"tab\there \u{2764}");
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct QuotedString {
+    #[quoth(span)]
+    span: Span,
+    body: Span,
+    value: String,
+}
+
+impl QuotedString {
+    /// Parses a [`QuotedString`] recognizing the given [`Escapes`] from the [`ParseStream`].
+    ///
+    /// On error the [`ParseStream`] is not advanced.
+    pub fn parse_with(stream: &mut ParseStream, escapes: &Escapes) -> Result<Self> {
+        let start_position = stream.mark();
+        let opener = stream.parse_exact_char('"')?;
+        let body_position = stream.mark();
+        let mut value = String::new();
+        loop {
+            match stream.peek_char() {
+                Some('"') => break,
+                Some('\\') => {
+                    stream.position += 1;
+                    match stream.parse_escape_with(escapes) {
+                        Ok(c) => value.push(c),
+                        Err(err) => {
+                            stream.position = start_position;
+                            return Err(err);
+                        }
+                    }
+                }
+                Some(c) => {
+                    value.push(c);
+                    stream.position += 1;
+                }
+                None => {
+                    stream.position = start_position;
+                    return Err(Error::new(opener, "unterminated string, expected `\"`"));
+                }
+            }
+        }
+        let body = stream.span_from(body_position);
+        stream.position += 1;
+        Ok(QuotedString {
+            span: stream.span_from(start_position),
+            body,
+            value,
+        })
+    }
+
+    /// Returns the [`Span`] of the raw text between the quotes, before escapes are decoded.
+    pub fn body(&self) -> &Span {
+        &self.body
+    }
+
+    /// Returns the decoded text between the quotes.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl Parsable for QuotedString {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        QuotedString::parse_with(stream, &Escapes::default())
+    }
+}
+
+/// A single-quoted character literal such as `'a'` or `'\n'`, in which an escape sequence is
+/// decoded as by [`ParseStream::parse_escape`].
+///
+/// Use [`CharLiteral::parse_with`] to recognize different [`Escapes`].
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct CharLiteral {
+    #[quoth(span)]
+    span: Span,
+    value: char,
+}
+
+impl CharLiteral {
+    /// Parses a [`CharLiteral`] recognizing the given [`Escapes`] from the [`ParseStream`].
+    ///
+    /// On error the [`ParseStream`] is not advanced.
+    pub fn parse_with(stream: &mut ParseStream, escapes: &Escapes) -> Result<Self> {
+        let start_position = stream.mark();
+        let opener = stream.parse_exact_char('\'')?;
+        let value = match stream.peek_char() {
+            Some('\'') => {
+                let err = Error::new(
+                    stream.span_from(start_position).with_len_chars(2),
+                    "empty character literal",
+                );
+                stream.position = start_position;
+                return Err(err);
+            }
+            Some('\\') => {
+                stream.position += 1;
+                stream.parse_escape_with(escapes)
+            }
+            _ => stream.parse_char(),
+        };
+        match value {
+            Ok(value) if stream.parse_exact_char('\'').is_ok() => Ok(CharLiteral {
+                span: stream.span_from(start_position),
+                value,
+            }),
+            Ok(_) => {
+                stream.position = start_position;
+                Err(Error::new(
+                    opener,
+                    "unterminated character literal, expected `'`",
+                ))
+            }
+            Err(err) => {
+                stream.position = start_position;
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns the decoded character.
+    pub fn value(&self) -> char {
+        self.value
+    }
+}
+
+impl Parsable for CharLiteral {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        CharLiteral::parse_with(stream, &Escapes::default())
+    }
+}
+
+#[test]
+fn test_parse_raw_string() {
+    let mut stream = ParseStream::from(r#"r"a\nb" rest"#);
+    let parsed = stream.parse::<RawString>().unwrap();
+    assert_eq!(parsed.body().source_text(), r"a\nb");
+    assert_eq!(parsed.hashes(), 0);
+    assert_eq!(stream.remaining(), " rest");
+    let mut stream = ParseStream::from(r###"r##"a "# b "#"##"###);
+    let parsed = stream.parse::<RawString>().unwrap();
+    assert_eq!(parsed.body().source_text(), r##"a "# b "#"##);
+    assert_eq!(parsed.hashes(), 2);
+    assert_eq!(parsed.to_string(), r###"r##"a "# b "#"##"###);
+    assert!(stream.remaining().is_empty());
+    let parsed = ParseStream::from("r#\"\"#").parse::<RawString>().unwrap();
+    assert_eq!(parsed.body().source_text(), "");
+}
+
+#[test]
+fn test_parse_raw_string_errors() {
+    let mut stream = ParseStream::from(r##"x r#"never "closed"##);
+    stream.position = 2;
+    let err = stream.parse::<RawString>().unwrap_err();
+    assert_eq!(err.span().source_text(), "r#\"");
+    assert_eq!(err.message(), "unterminated raw string, expected `\"#`");
+    assert_eq!(stream.position, 2);
+    let mut stream = ParseStream::from("r#x");
+    assert!(stream.parse::<RawString>().is_err());
+    assert_eq!(stream.position, 0);
+    assert!(ParseStream::from("\"x\"").parse::<RawString>().is_err());
+}
+
+#[test]
+fn test_parse_template_text() {
+    let mut stream = ParseStream::from("a {{b}} c{x}d}}");
+    let parsed = stream.parse::<TemplateText>().unwrap();
+    assert_eq!(parsed.text(), "a {b} c");
+    assert_eq!(parsed.span().source_text(), "a {{b}} c");
+    assert_eq!(stream.remaining(), "{x}d}}");
+    let err = stream.parse::<TemplateText>().unwrap_err();
+    assert_eq!(err.message(), "expected template text");
+    stream.position += 3;
+    let parsed = stream.parse::<TemplateText>().unwrap();
+    assert_eq!(parsed.text(), "d}");
+    assert!(stream.remaining().is_empty());
+    assert!(stream.parse::<TemplateText>().is_err());
+}
+
+#[test]
+fn test_parse_template_text_errors() {
+    let mut stream = ParseStream::from("a } b");
+    let err = stream.parse::<TemplateText>().unwrap_err();
+    assert_eq!(err.message(), "unmatched `}`, use `}}` for a literal brace");
+    assert_eq!(err.span().byte_range(), &(2..3));
+    assert_eq!(stream.position, 0);
+    let mut stream = ParseStream::from("a {{ {b");
+    let err = stream.parse::<TemplateText>().unwrap_err();
+    assert_eq!(err.message(), "unclosed `{`, use `{{` for a literal brace");
+    assert_eq!(err.span().byte_range(), &(5..6));
+    assert_eq!(stream.position, 0);
+    let mut stream = ParseStream::from("a {b c {d}");
+    let err = stream.parse::<TemplateText>().unwrap_err();
+    assert_eq!(err.message(), "unclosed `{`, use `{{` for a literal brace");
+    assert_eq!(err.span().byte_range(), &(2..3));
+    assert_eq!(stream.position, 0);
+}
+
+#[test]
+fn test_parse_escape() {
+    let decode = |input: &str| ParseStream::from(input).parse_escape();
+    assert_eq!(decode("n").unwrap(), '\n');
+    assert_eq!(decode("\\").unwrap(), '\\');
+    assert_eq!(decode("0").unwrap(), '\0');
+    assert_eq!(decode("x41").unwrap(), 'A');
+    assert_eq!(decode("u{e9}").unwrap(), 'é');
+    assert_eq!(decode("u{10FFFF}").unwrap(), '\u{10ffff}');
+    let error = |input: &str| {
+        let mut stream = ParseStream::from(input);
+        let err = stream.parse_escape().unwrap_err();
+        assert_eq!(stream.position, 0);
+        (err.message().to_string(), err.span().byte_range().clone())
+    };
+    assert_eq!(error(""), ("expected escape sequence".to_string(), 0..0));
+    assert_eq!(
+        error("q"),
+        ("unknown escape sequence `\\q`".to_string(), 0..1)
+    );
+    assert_eq!(
+        error("x4g"),
+        ("expected 2 hex digits in `\\x` escape".to_string(), 2..3)
+    );
+    assert_eq!(error("x80").1, 1..3);
+    assert_eq!(error("u41").0, "expected `{` after `\\u`");
+    assert_eq!(error("u{}").1, 2..3);
+    assert_eq!(error("u{1234567}").1, 2..9);
+    assert_eq!(error("u{41").0, "expected `}` to close `\\u{...}` escape");
+    assert_eq!(
+        error("u{d800}"),
+        (
+            "invalid unicode escape, `d800` is not a character".to_string(),
+            2..6
+        )
+    );
+    let escapes = Escapes::none().with_simple('n', '\n');
+    assert_eq!(escapes.simple(), [('n', '\n')]);
+    let mut stream = ParseStream::from("t");
+    assert!(stream.parse_escape_with(&escapes).is_err());
+}
+
+#[test]
+fn test_parse_quoted_string() {
+    let mut stream = ParseStream::from(r#""a\"b\\c\x41" rest"#);
+    let parsed = stream.parse::<QuotedString>().unwrap();
+    assert_eq!(parsed.value(), "a\"b\\cA");
+    assert_eq!(parsed.to_string(), r#""a\"b\\c\x41""#);
+    assert_eq!(stream.remaining(), " rest");
+    assert_eq!(
+        ParseStream::from("\"\"")
+            .parse::<QuotedString>()
+            .unwrap()
+            .value(),
+        ""
+    );
+    let mut stream = ParseStream::from(r#""ab\q""#);
+    let err = stream.parse::<QuotedString>().unwrap_err();
+    assert_eq!(err.span().byte_range(), &(4..5));
+    assert_eq!(stream.position, 0);
+    let err = ParseStream::from("\"never")
+        .parse::<QuotedString>()
+        .unwrap_err();
+    assert_eq!(err.message(), "unterminated string, expected `\"`");
+    assert_eq!(err.span().byte_range(), &(0..1));
+}
+
+#[test]
+fn test_parse_char_literal() {
+    let parse = |input: &str| ParseStream::from(input).parse::<CharLiteral>();
+    assert_eq!(parse("'a'").unwrap().value(), 'a');
+    assert_eq!(parse("'é' x").unwrap().span().source_text(), "'é'");
+    assert_eq!(parse(r"'\''").unwrap().value(), '\'');
+    assert_eq!(parse(r"'\u{1F600}'").unwrap().value(), '😀');
+    assert_eq!(
+        parse("''").unwrap_err().message(),
+        "empty character literal"
+    );
+    let err = parse("'ab'").unwrap_err();
+    assert_eq!(
+        err.message(),
+        "unterminated character literal, expected `'`"
+    );
+    assert_eq!(err.span().byte_range(), &(0..1));
+    assert_eq!(parse(r"'\z'").unwrap_err().span().byte_range(), &(2..3));
+}
@@ -0,0 +1,215 @@
+use super::*;
+
+use crate as quoth;
+
+/// Which comments a [`Trivia`] recognizes, see [`Trivia::parse_with`].
+///
+/// The default recognizes `//` line comments and nesting `/* ... */` block comments.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct TriviaOptions {
+    line_comment: Option<String>,
+    block_comment: Option<(String, String)>,
+    nested: bool,
+}
+
+impl Default for TriviaOptions {
+    fn default() -> Self {
+        TriviaOptions {
+            line_comment: Some("//".to_string()),
+            block_comment: Some(("/*".to_string(), "*/".to_string())),
+            nested: true,
+        }
+    }
+}
+
+impl TriviaOptions {
+    /// Creates [`TriviaOptions`] that recognize only whitespace, and no comments.
+    pub fn whitespace_only() -> TriviaOptions {
+        TriviaOptions {
+            line_comment: None,
+            block_comment: None,
+            nested: false,
+        }
+    }
+
+    /// Sets the start token of line comments, or `None` to not recognize line comments.
+    pub fn line_comment(mut self, start: Option<impl ToString>) -> TriviaOptions {
+        self.line_comment = start.map(|start| start.to_string());
+        self
+    }
+
+    /// Sets the delimiters of block comments, or `None` to not recognize block comments.
+    pub fn block_comment(
+        mut self,
+        delimiters: Option<(impl ToString, impl ToString)>,
+    ) -> TriviaOptions {
+        self.block_comment = delimiters.map(|(open, close)| (open.to_string(), close.to_string()));
+        self
+    }
+
+    /// Sets whether block comments nest, see [`BlockComment::parse_with`].
+    pub fn nested(mut self, nested: bool) -> TriviaOptions {
+        self.nested = nested;
+        self
+    }
+}
+
+/// A single piece of a [`Trivia`].
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum TriviaPiece {
+    /// A run of whitespace, possibly spanning multiple lines.
+    Whitespace(Whitespace),
+    /// A [`LineComment`], excluding its terminating newline.
+    LineComment(LineComment),
+    /// A [`BlockComment`].
+    BlockComment(BlockComment),
+}
+
+impl TriviaPiece {
+    /// Returns `true` if this piece is a comment rather than whitespace.
+    pub fn is_comment(&self) -> bool {
+        !matches!(self, TriviaPiece::Whitespace(_))
+    }
+}
+
+impl Spanned for TriviaPiece {
+    fn span(&self) -> Span {
+        match self {
+            TriviaPiece::Whitespace(whitespace) => whitespace.span(),
+            TriviaPiece::LineComment(comment) => comment.span(),
+            TriviaPiece::BlockComment(comment) => comment.span(),
+        }
+    }
+}
+
+/// Any mix of whitespace and comments, kept as a list of [`TriviaPiece`]s so that formatters
+/// can preserve and reposition comments.
+///
+/// [`Trivia`] may be empty, so parsing it never fails except on an unterminated block comment,
+/// in which case the [`ParseStream`] is not advanced. By default `//` line comments and
+/// nesting `/* ... */` block comments are recognized; use [`Trivia::parse_with`] with
+/// [`TriviaOptions`] to recognize other comment syntaxes.
+///
+/// ```
+/// use quoth::{parsable::Trivia, *};
+///
+/// let mut stream = ParseStream::from("  // note\n  /* more */ x");
+/// let trivia = stream.parse::<Trivia>().unwrap();
+/// assert_eq!(trivia.pieces().len(), 5);
+/// assert_eq!(trivia.comments().count(), 2);
+/// assert_eq!(stream.remaining(), "x");
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct Trivia {
+    #[quoth(span)]
+    span: Span,
+    pieces: Vec<TriviaPiece>,
+}
+
+impl Trivia {
+    /// Parses [`Trivia`] recognizing the comments described by `options` from the
+    /// [`ParseStream`].
+    pub fn parse_with(stream: &mut ParseStream, options: &TriviaOptions) -> Result<Self> {
+        let start_position = stream.mark();
+        let mut pieces = Vec::new();
+        loop {
+            let piece = if stream.peek::<Whitespace>() {
+                TriviaPiece::Whitespace(stream.parse()?)
+            } else if let Some((open, close)) = options
+                .block_comment
+                .as_ref()
+                .filter(|(open, _)| stream.peek_str(open))
+            {
+                match BlockComment::parse_with(stream, open, close, options.nested) {
+                    Ok(comment) => TriviaPiece::BlockComment(comment),
+                    Err(err) => {
+                        stream.position = start_position;
+                        return Err(err);
+                    }
+                }
+            } else if let Some(start) = options
+                .line_comment
+                .as_ref()
+                .filter(|start| stream.peek_str(start))
+            {
+                TriviaPiece::LineComment(LineComment::parse_with(stream, start)?)
+            } else {
+                break;
+            };
+            pieces.push(piece);
+        }
+        Ok(Trivia {
+            span: stream.span_from(start_position),
+            pieces,
+        })
+    }
+
+    /// Returns the pieces of this [`Trivia`], in order.
+    pub fn pieces(&self) -> &[TriviaPiece] {
+        &self.pieces
+    }
+
+    /// Returns an iterator over the comments of this [`Trivia`], skipping whitespace.
+    pub fn comments(&self) -> impl Iterator<Item = &TriviaPiece> {
+        self.pieces.iter().filter(|piece| piece.is_comment())
+    }
+
+    /// Returns `true` if this [`Trivia`] contains no whitespace or comments.
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+}
+
+impl Parsable for Trivia {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        Trivia::parse_with(stream, &TriviaOptions::default())
+    }
+}
+
+#[test]
+fn test_parse_trivia() {
+    let mut stream = ParseStream::from(" /* a /* b */ */// c\r\n\t// d\nx");
+    let trivia = stream.parse::<Trivia>().unwrap();
+    let pieces: Vec<_> = trivia
+        .pieces()
+        .iter()
+        .map(|piece| (piece.is_comment(), piece.span().source_text().to_string()))
+        .collect();
+    assert_eq!(
+        pieces,
+        [
+            (false, " ".to_string()),
+            (true, "/* a /* b */ */".to_string()),
+            (true, "// c".to_string()),
+            (false, "\r\n\t".to_string()),
+            (true, "// d".to_string()),
+            (false, "\n".to_string()),
+        ]
+    );
+    assert_eq!(trivia.to_string(), " /* a /* b */ */// c\r\n\t// d\n");
+    assert_eq!(stream.remaining(), "x");
+    let trivia = stream.parse::<Trivia>().unwrap();
+    assert!(trivia.is_empty());
+    assert_eq!(stream.remaining(), "x");
+}
+
+#[test]
+fn test_parse_trivia_with_options() {
+    let options = TriviaOptions::whitespace_only().line_comment(Some("#"));
+    let mut stream = ParseStream::from("# a\n // b");
+    let trivia = Trivia::parse_with(&mut stream, &options).unwrap();
+    assert_eq!(trivia.comments().count(), 1);
+    assert_eq!(stream.remaining(), "// b");
+    let options = TriviaOptions::default()
+        .line_comment(None::<&str>)
+        .block_comment(Some(("(*", "*)")))
+        .nested(false);
+    let mut stream = ParseStream::from("(* a (* b *) // c");
+    let trivia = Trivia::parse_with(&mut stream, &options).unwrap();
+    assert_eq!(trivia.pieces().len(), 2);
+    assert_eq!(stream.remaining(), "// c");
+    let mut stream = ParseStream::from("  /* never closed");
+    let err = stream.parse::<Trivia>().unwrap_err();
+    assert!(err.message().contains("unterminated block comment"));
+    assert_eq!(stream.position, 0);
+}
@@ -17,10 +17,69 @@ impl Parsable for Whitespace {
         if start_position == stream.position {
             return Err(Error::new(stream.current_span(), "expected whitespace"));
         }
-        Ok(Whitespace(Span::new(
-            stream.source().clone(),
-            start_position..stream.position,
-        )))
+        Ok(Whitespace(stream.span_from(start_position)))
+    }
+}
+
+/// The kind of line terminator matched by a [`Newline`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum NewlineKind {
+    /// A Unix-style line feed, `\n`.
+    Lf,
+    /// A Windows-style carriage return followed by a line feed, `\r\n`.
+    CrLf,
+    /// A lone carriage return, `\r`.
+    Cr,
+}
+
+/// Exactly one line terminator: `\n`, `\r\n`, or `\r`.
+///
+/// Unlike [`Whitespace`], which consumes any amount of whitespace including line breaks,
+/// [`Newline`] consumes a single line terminator and reports which kind it was.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct Newline {
+    span: Span,
+    kind: NewlineKind,
+}
+
+impl Newline {
+    /// Returns which line terminator this [`Newline`] matched.
+    pub fn kind(&self) -> NewlineKind {
+        self.kind
+    }
+}
+
+impl Parsable for Newline {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        let start_position = stream.position;
+        let kind = match (stream.peek_char(), stream.peek_nth(1)) {
+            (Some('\r'), Some('\n')) => NewlineKind::CrLf,
+            (Some('\r'), _) => NewlineKind::Cr,
+            (Some('\n'), _) => NewlineKind::Lf,
+            _ => return Err(Error::new(stream.current_span(), "expected newline")),
+        };
+        stream.consume(if kind == NewlineKind::CrLf { 2 } else { 1 })?;
+        Ok(Newline {
+            span: stream.span_from(start_position),
+            kind,
+        })
+    }
+}
+
+/// One or more spaces and/or tabs, i.e. horizontal whitespace that never crosses a line break.
+#[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+pub struct SpaceOrTab(Span);
+
+impl Parsable for SpaceOrTab {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        let start_position = stream.position;
+        while let Some(' ' | '\t') = stream.peek_char() {
+            stream.consume(1)?;
+        }
+        if start_position == stream.position {
+            return Err(Error::new(stream.current_span(), "expected space or tab"));
+        }
+        Ok(SpaceOrTab(stream.span_from(start_position)))
     }
 }
 
@@ -33,3 +92,44 @@ fn test_parse_whitespace() {
     let parsed = stream.parse::<Whitespace>().unwrap();
     assert_eq!(parsed.span().source_text(), "\t\t  \n ");
 }
+
+#[test]
+fn test_parse_newline() {
+    let mut stream = ParseStream::from("\n\r\n\r\r\n\n x");
+    let kinds: Vec<_> = (0..5)
+        .map(|_| stream.parse::<Newline>().unwrap().kind())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            NewlineKind::Lf,
+            NewlineKind::CrLf,
+            NewlineKind::Cr,
+            NewlineKind::CrLf,
+            NewlineKind::Lf
+        ]
+    );
+    let err = stream.parse::<Newline>().unwrap_err();
+    assert!(err.to_string().contains("expected newline"));
+    assert_eq!(stream.remaining(), " x");
+    let mut stream = ParseStream::from("\r\nrest");
+    assert_eq!(
+        stream.parse::<Newline>().unwrap().span().source_text(),
+        "\r\n"
+    );
+    assert_eq!(stream.remaining(), "rest");
+}
+
+#[test]
+fn test_parse_space_or_tab() {
+    let mut stream = ParseStream::from(" \t  \n  x");
+    let parsed = stream.parse::<SpaceOrTab>().unwrap();
+    assert_eq!(parsed.span().source_text(), " \t  ");
+    assert!(stream.parse::<SpaceOrTab>().is_err());
+    stream.parse::<Newline>().unwrap();
+    assert_eq!(
+        stream.parse::<SpaceOrTab>().unwrap().span().source_text(),
+        "  "
+    );
+    assert_eq!(stream.remaining(), "x");
+}
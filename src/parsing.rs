@@ -5,15 +5,24 @@ use core::{
     hash::Hash,
 };
 use regex::Regex;
-use std::{cmp::min, ops::Deref, rc::Rc, str::FromStr};
+use std::{
+    cell::{Cell, RefCell},
+    cmp::min,
+    collections::HashMap,
+    ops::Deref,
+    rc::Rc,
+    str::FromStr,
+};
 
-use self::parsable::Exact;
+use self::parsable::{Exact, Optional};
 
 use super::*;
 
 /// Represents an error that occurred during parsing.
+///
+/// The underlying [`Diagnostic`] is boxed to keep [`Result`] small on the happy path.
 #[derive(Clone, PartialEq, Eq, Hash)]
-pub struct Error(Diagnostic);
+pub struct Error(Box<Diagnostic>);
 
 impl Deref for Error {
     type Target = Diagnostic;
@@ -23,6 +32,28 @@ impl Deref for Error {
     }
 }
 
+impl From<Error> for Diagnostic {
+    fn from(error: Error) -> Self {
+        error.into_diagnostic()
+    }
+}
+
+impl From<std::io::Error> for Error {
+    /// Converts an IO error, e.g. from [`Source::from_file`], into an [`Error`] with a
+    /// [`Span::blank`] span, so that reading and parsing can share one error type.
+    fn from(error: std::io::Error) -> Self {
+        Error::new(Span::blank(), error)
+    }
+}
+
+impl From<regex::Error> for Error {
+    /// Converts a regex compilation error, e.g. from [`ParseStream::try_parse_regex`], into an
+    /// [`Error`] with a [`Span::blank`] span.
+    fn from(error: regex::Error) -> Self {
+        Error::new(Span::blank(), error)
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -38,24 +69,65 @@ impl Debug for Error {
 impl Error {
     /// Creates a new [`Error`] with the given [`Span`] and message.
     pub fn new(span: Span, message: impl ToString) -> Error {
-        Error(Diagnostic::new(
+        Error(Box::new(Diagnostic::new(
             DiagnosticLevel::Error,
             span,
             message,
             Option::<String>::None,
             Vec::new(),
-        ))
+        )))
     }
 
     /// Creates a new [`Error`] expecting a certain value at the given [`Span`].
     pub fn expected(span: Span, expected: impl Display) -> Error {
-        Error(Diagnostic::new(
+        Error(Box::new(Diagnostic::new(
             DiagnosticLevel::Error,
             span,
             format!("expected `{expected}`"),
             Option::<String>::None,
             Vec::new(),
-        ))
+        )))
+    }
+
+    /// Consumes this [`Error`], returning the underlying [`Diagnostic`], e.g. to modify it or
+    /// to [emit](ParseStream::emit) it and recover instead of aborting.
+    pub fn into_diagnostic(self) -> Diagnostic {
+        *self.0
+    }
+
+    /// Consumes this [`Error`], returning the underlying [`Diagnostic`] with its level set to
+    /// `level`, e.g. to downgrade an error to a [`DiagnosticLevel::Warning`] in a lenient mode.
+    pub fn with_level(self, level: DiagnosticLevel) -> Diagnostic {
+        let mut diagnostic = self.into_diagnostic();
+        diagnostic.set_level(level);
+        diagnostic
+    }
+
+    /// Attaches the given child [`Diagnostic`] to this [`Error`].
+    pub fn with_child(self, child: Diagnostic) -> Error {
+        Error(Box::new(self.0.with_child(child)))
+    }
+
+    /// Attaches a note with the given span and message to this [`Error`], e.g. a "defined
+    /// here" note pointing at an earlier [`Span`].
+    pub fn with_note(self, span: Span, message: impl ToString) -> Error {
+        Error(Box::new(self.0.with_note(span, message)))
+    }
+
+    /// Attaches a help message with the given span to this [`Error`].
+    pub fn with_help(self, span: Span, message: impl ToString) -> Error {
+        Error(Box::new(self.0.with_help(span, message)))
+    }
+
+    /// Sets the label of the primary span of this [`Error`], see
+    /// [`Diagnostic::with_primary_label`].
+    pub fn with_primary_label(self, label: impl ToString) -> Error {
+        Error(Box::new(self.0.with_primary_label(label)))
+    }
+
+    /// Attaches a secondary labeled [`Span`] to this [`Error`], see [`Diagnostic::with_label`].
+    pub fn with_label(self, span: Span, label: impl ToString) -> Error {
+        Error(Box::new(self.0.with_label(span, label)))
     }
 }
 
@@ -68,7 +140,53 @@ pub struct ParseStream {
     source: Rc<Source>,
     /// The current parsing position in the source text as an offset from the beginning of the
     /// source. Advancing this position will consume characters from the source.
+    ///
+    /// The position is a _character_ index into the source's [`IndexedString`], not a byte
+    /// offset, so advancing it by one always moves past exactly one (possibly multi-byte)
+    /// character.
+    ///
+    /// Positions past the end of the input are treated as being at the end of the input. Use
+    /// [`ParseStream::seek`] to move to a position with bounds checking.
     pub position: usize,
+    diagnostics: Rc<DiagnosticBag>,
+    depth: Rc<DepthCounter>,
+    max_depth: usize,
+}
+
+/// The default limit on how deeply [`ParseStream::enter`] may be nested, see
+/// [`ParseStream::set_max_depth`].
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// The current nesting depth of a [`ParseStream`], shared between it and its forks.
+#[derive(Debug, Default)]
+struct DepthCounter(Cell<usize>);
+
+impl PartialEq for DepthCounter {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.get() == other.0.get()
+    }
+}
+
+impl Eq for DepthCounter {}
+
+impl Hash for DepthCounter {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.0.get().hash(state);
+    }
+}
+
+/// Tracks one level of nesting entered via [`ParseStream::enter`], leaving it again when
+/// dropped.
+#[must_use = "the nesting level is left as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct DepthGuard {
+    depth: Rc<DepthCounter>,
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        self.depth.0.set(self.depth.0.get() - 1);
+    }
 }
 
 impl ParseStream {
@@ -79,17 +197,91 @@ impl ParseStream {
 
     /// Returns the current [`Span`] of the [`ParseStream`]. This [`Span`] represents the
     /// current character being parsed.
+    ///
+    /// If [`ParseStream::position`] is past the end of the input, this is a zero-length
+    /// [`Span`] at the end of the input.
     pub fn current_span(&self) -> Span {
+        let position = self.clamped_position();
         Span::new(
             self.source.clone(),
-            self.position..(min(self.source().len(), self.position + 1)),
+            position..min(self.source().len(), position + 1),
         )
     }
 
-    /// Returns the remaining [`Span`] of the [`ParseStream`]. This [`Span`] represents the remaining
+    /// Returns the current [`ParseStream::position`], to be passed to
+    /// [`ParseStream::span_from`] once parsing is done.
+    ///
+    /// ```
+    /// use quoth::*;
+    ///
+    /// let mut stream = ParseStream::from("abc");
+    /// let mark = stream.mark();
+    /// stream.consume(2).unwrap();
+    /// assert_eq!(stream.span_from(mark).source_text(), "ab");
+    /// ```
+    pub fn mark(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the [`Span`] from the specified position, typically obtained from
+    /// [`ParseStream::mark`], up to the current position, i.e. the span of everything consumed
+    /// since then.
     ///
+    /// If `start` is past the current position (e.g. because the stream was rewound after the
+    /// mark was taken), a zero-length [`Span`] at the current position is returned.
+    pub fn span_from(&self, start: usize) -> Span {
+        let end = self.clamped_position();
+        Span::new(self.source.clone(), start.min(end)..end)
+    }
+
+    /// Returns the line and column of the current position of the [`ParseStream`].
+    ///
+    /// This is a binary search over the line starts of the [`Source`], see
+    /// [`Source::line_col`].
+    pub fn line_col(&self) -> LineCol {
+        self.source.line_col(self.position)
+    }
+
+    /// Returns `true` if the current position is at the very start of a line, i.e. at the
+    /// start of the input or immediately after a newline.
+    pub fn at_line_start(&self) -> bool {
+        self.line_col().col == 0
+    }
+
+    /// Returns the remaining [`Span`] of the [`ParseStream`]. This [`Span`] represents the
+    /// remaining text that has not been parsed yet, see [`ParseStream::remaining`].
     pub fn remaining_span(&self) -> Span {
-        Span::new(self.source.clone(), self.position..self.source.len())
+        Span::new(
+            self.source.clone(),
+            self.clamped_position()..self.source.len(),
+        )
+    }
+
+    /// Moves the [`ParseStream`] to the specified position, e.g. to rewind to a position that
+    /// was saved earlier.
+    ///
+    /// Returns an error, leaving the position unchanged, if `position` is past the end of the
+    /// input. Prefer this over setting [`ParseStream::position`] directly.
+    pub fn seek(&mut self, position: usize) -> Result<()> {
+        let len = self.source.len();
+        if position > len {
+            return Err(Error::new(
+                Span::new(self.source.clone(), len..len),
+                format!("cannot seek to position {position}, input is only {len} characters long"),
+            ));
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    /// Rewinds the [`ParseStream`] to the start of its input.
+    pub fn reset(&mut self) {
+        self.position = 0;
+    }
+
+    /// Returns [`ParseStream::position`], clamped to the end of the input.
+    fn clamped_position(&self) -> usize {
+        min(self.position, self.source.len())
     }
 
     /// Attempts to parse a value of type `T` from the [`ParseStream`].
@@ -102,9 +294,117 @@ impl ParseStream {
         T::parse_value(value, self)
     }
 
-    /// note: panics upon invalid regex syntax
+    /// Attempts to parse a value of type `T` from the [`ParseStream`], transforming it with
+    /// `f` on success.
+    ///
+    /// ```
+    /// use quoth::{parsable::numbers::U64, *};
+    ///
+    /// let mut stream = ParseStream::from("42");
+    /// let n = stream.parse_map(|n: U64| n.value() * 2).unwrap();
+    /// assert_eq!(n, 84);
+    /// ```
+    pub fn parse_map<T: Parsable, U>(&mut self, f: impl FnOnce(T) -> U) -> Result<U> {
+        self.parse::<T>().map(f)
+    }
+
+    /// Attempts to parse a value of type `T` from the [`ParseStream`], transforming it with
+    /// the fallible `f` on success, e.g. to validate it.
+    ///
+    /// If either parsing or `f` fails, the [`ParseStream`] is not advanced.
+    pub fn parse_try_map<T: Parsable, U>(&mut self, f: impl FnOnce(T) -> Result<U>) -> Result<U> {
+        self.transaction(|stream| f(stream.parse::<T>()?))
+    }
+
+    /// Parses a `T` from the [`ParseStream`] if `cond` is `true`, and otherwise returns
+    /// [`Optional::None`] without consuming anything.
+    pub fn parse_if<T: Parsable>(&mut self, cond: bool) -> Result<Optional<T>> {
+        if !cond {
+            return Ok(Optional::None);
+        }
+        self.parse::<T>().map(Optional::Some)
+    }
+
+    /// Parses a `T` from the [`ParseStream`] if it [peeks](ParseStream::peek) a `P` at the
+    /// current position, and otherwise returns [`Optional::None`] without consuming anything.
+    ///
+    /// The `P` is not consumed, so it is typically the start of the `T`, e.g. a keyword.
+    /// Errors from parsing the `T` are returned as-is.
+    pub fn parse_when<T: Parsable, P: Peekable>(&mut self) -> Result<Optional<T>> {
+        let cond = self.peek::<P>();
+        self.parse_if(cond)
+    }
+
+    /// Parses an `Open` delimiter, a possibly empty list of `T`s separated by `Sep`s, and a
+    /// `Close` delimiter from the [`ParseStream`], such as `[a, b, c]`.
+    ///
+    /// This is shorthand for parsing a [`Delimited`](parsable::Delimited), see there for how
+    /// trailing separators and unclosed delimiters are handled. On error the [`ParseStream`]
+    /// is not advanced.
+    pub fn parse_delimited_list<Open, T, Sep, Close>(
+        &mut self,
+    ) -> Result<parsable::Delimited<Open, T, Sep, Close>>
+    where
+        Open: Parsable,
+        T: Parsable,
+        Sep: Parsable,
+        Close: Parsable,
+    {
+        self.parse()
+    }
+
+    /// Attempts to parse a value of type `T` from the [`ParseStream`], replacing any error with
+    /// one reading "expected {what}" at the current position.
+    ///
+    /// The original error is kept as a child of the new one, so the low-level reason for the
+    /// failure is still reported. On failure the [`ParseStream`] is not advanced.
+    ///
+    /// ```
+    /// use quoth::{parsable::numbers::U64, *};
+    ///
+    /// let mut stream = ParseStream::from("port = x");
+    /// stream.position = 7;
+    /// let err = stream.expect::<U64>("a port number").unwrap_err();
+    /// assert_eq!(err.message(), "expected a port number");
+    /// assert_eq!(err.children()[0].message(), "expected digit");
+    /// ```
+    pub fn expect<T: Parsable>(&mut self, what: impl Display) -> Result<T> {
+        let start_position = self.position;
+        let start_span = self.current_span();
+        self.parse::<T>().map_err(|err| {
+            self.position = start_position;
+            Error::new(start_span, format!("expected {what}")).with_child(err.into())
+        })
+    }
+
+    /// Attempts to parse a match of the specified regex pattern, which must match at the
+    /// current position, from the [`ParseStream`].
+    ///
+    /// Patterns given as strings are compiled once and then cached (see [`Pattern`]), so this
+    /// can be called with the same pattern in a hot loop without recompiling it each time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `reg` is not valid regex syntax. Use [`ParseStream::try_parse_regex`] for
+    /// patterns that are not known to be valid ahead of time, e.g. ones from configuration.
     pub fn parse_regex(&mut self, reg: impl Pattern) -> Result<Exact> {
-        let reg = reg.to_regex();
+        self.parse_compiled_regex(&reg.to_regex())
+    }
+
+    /// Like [`ParseStream::parse_regex`], but returns a [`regex::Error`] instead of panicking
+    /// if `reg` is not valid regex syntax.
+    ///
+    /// The outer [`Result`](core::result::Result) reports whether the pattern compiled, and the
+    /// inner [`Result`] whether it matched. The [`ParseStream`] is not advanced if the pattern
+    /// fails to compile.
+    pub fn try_parse_regex(
+        &mut self,
+        reg: impl Pattern,
+    ) -> core::result::Result<Result<Exact>, regex::Error> {
+        Ok(self.parse_compiled_regex(&reg.try_to_regex()?))
+    }
+
+    fn parse_compiled_regex(&mut self, reg: &Regex) -> Result<Exact> {
         match reg.find(self.remaining().as_str()) {
             Some(m) => {
                 if m.start() > 0 {
@@ -114,11 +414,8 @@ impl ParseStream {
                     ));
                 }
                 let start_position = self.position;
-                self.position += m.as_str().len();
-                Ok(Exact::new(Span::new(
-                    self.source.clone(),
-                    start_position..self.position,
-                )))
+                self.position += m.as_str().chars().count();
+                Ok(Exact::new(self.span_from(start_position)))
             }
             None => Err(Error::new(
                 self.current_span(),
@@ -130,10 +427,12 @@ impl ParseStream {
     /// Peeks at the [`ParseStream`] to see if it can parse the specified regex pattern as the
     /// next value in the [`Source`].
     ///
-    /// note: panics upon invalid regex syntax
-    ///
     /// Analogue of [`ParseStream::parse_regex`].
-    pub fn peek_regex(&self, reg: Regex) -> bool {
+    ///
+    /// # Panics
+    ///
+    /// Panics if `reg` is not valid regex syntax.
+    pub fn peek_regex(&self, reg: impl Pattern) -> bool {
         self.fork().parse_regex(reg).is_ok()
     }
 
@@ -177,16 +476,114 @@ impl ParseStream {
     pub fn peek_istr(&self, s: impl ToString) -> bool {
         self.remaining()
             .to_lowercase()
-            .starts_with(&s.to_string().to_lowercase())
+            .starts_with(s.to_string().to_lowercase())
+    }
+
+    /// Peeks at the [`ParseStream`] to see if it can parse the specified keyword as the next
+    /// value.
+    ///
+    /// Unlike [`ParseStream::peek_str`], the keyword must not be immediately followed by an
+    /// identifier character (alphanumeric or `_`), so `peek_keyword("for")` is `false` for
+    /// `foreign` but `true` for `for x` or `for(`.
+    ///
+    /// Analogue of [`ParseStream::parse_keyword`].
+    pub fn peek_keyword(&self, kw: impl AsRef<str>) -> bool {
+        let kw = kw.as_ref();
+        self.peek_str(kw) && !self.peek_nth(kw.chars().count()).is_some_and(is_ident_char)
+    }
+
+    /// Attempts to parse the specified keyword from the [`ParseStream`], see
+    /// [`ParseStream::peek_keyword`].
+    ///
+    /// If the keyword is only present as the prefix of a longer word, the returned error spans
+    /// that whole word. The [`ParseStream`] is not advanced if this returns an error.
+    ///
+    /// Analogue of [`ParseStream::peek_keyword`].
+    pub fn parse_keyword(&mut self, kw: impl AsRef<str>) -> Result<Exact> {
+        let kw = kw.as_ref();
+        if self.peek_keyword(kw) {
+            return self.parse_str(kw);
+        }
+        if !self.peek_str(kw) {
+            return Err(Error::expected(self.current_span(), kw));
+        }
+        let start = self.position;
+        let mut end = start + kw.chars().count();
+        while self.source.char_at(end).is_some_and(is_ident_char) {
+            end += 1;
+        }
+        Err(Error::new(
+            Span::new(self.source.clone(), start..end),
+            format!("expected keyword `{kw}`"),
+        ))
+    }
+
+    /// Tries each of the `alternatives` on a fork of the [`ParseStream`] and commits to the
+    /// one that consumed the most input, i.e. maximal munch.
+    ///
+    /// Ties are broken in favor of the earliest alternative. If every alternative fails, the
+    /// error of the one that got furthest into the input is returned (again preferring the
+    /// earliest), and the [`ParseStream`] is not advanced. See [`choose_longest!`] to map
+    /// several [`Parsable`] types to a common type instead of writing closures.
+    ///
+    /// ```
+    /// use quoth::{parsable::numbers::U64, *};
+    ///
+    /// let hex = |stream: &mut ParseStream| {
+    ///     let digits = stream.parse_regex("0x[0-9a-fA-F]+")?;
+    ///     Ok(u64::from_str_radix(&digits.span().source_text().as_str()[2..], 16).unwrap())
+    /// };
+    /// let dec = |stream: &mut ParseStream| stream.parse::<U64>().map(|n| n.value());
+    /// let mut stream = ParseStream::from("0x1F");
+    /// assert_eq!(stream.parse_longest_of([&dec, &hex]).unwrap(), 31);
+    /// let mut stream = ParseStream::from("0xyz");
+    /// assert_eq!(stream.parse_longest_of([&dec, &hex]).unwrap(), 0);
+    /// assert_eq!(stream.remaining(), "xyz");
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn parse_longest_of<T, const N: usize>(
+        &mut self,
+        alternatives: [&dyn Fn(&mut ParseStream) -> Result<T>; N],
+    ) -> Result<T> {
+        let mut longest: Option<(ParseStream, T)> = None;
+        let mut furthest: Option<Error> = None;
+        for alternative in alternatives {
+            let mut fork = self.fork();
+            match alternative(&mut fork) {
+                Ok(value) => {
+                    if longest
+                        .as_ref()
+                        .is_none_or(|(best, _)| fork.position > best.position)
+                    {
+                        longest = Some((fork, value));
+                    }
+                }
+                Err(err) => {
+                    if furthest.as_ref().is_none_or(|best| {
+                        err.span().byte_range().start > best.span().byte_range().start
+                    }) {
+                        furthest = Some(err);
+                    }
+                }
+            }
+        }
+        match (longest, furthest) {
+            (Some((fork, value)), _) => {
+                *self = fork;
+                Ok(value)
+            }
+            (None, Some(err)) => Err(err),
+            (None, None) => Err(Error::new(self.current_span(), "no alternatives to parse")),
+        }
     }
 
     /// Attempts to parse any value of the specified values from the [`ParseStream`].
     ///
     /// Analogue of [`ParseStream::peek_any_value_of`].
     pub fn parse_any_value_of<T: Parsable, const N: usize>(&mut self, values: [T; N]) -> Result<T> {
-        for i in 0..N {
-            if self.peek_value(values[i].clone()) {
-                return self.parse_value(values[i].clone());
+        for value in &values {
+            if self.peek_value(value.clone()) {
+                return self.parse_value(value.clone());
             }
         }
         Err(Error::new(
@@ -254,6 +651,30 @@ impl ParseStream {
         ))
     }
 
+    /// Like [`ParseStream::parse_any_str_of`], but skips any leading whitespace (as defined by
+    /// [`char::is_whitespace`]) before trying each of the specified strings.
+    ///
+    /// The returned [`Exact`] spans only the matched string, not the skipped whitespace,
+    /// although the whitespace is consumed. If none of the strings match, the whitespace is
+    /// not consumed and the error points at the first character after it.
+    ///
+    /// Analogue of [`ParseStream::peek_any_str_of_ws`].
+    pub fn parse_any_str_of_ws<const N: usize>(
+        &mut self,
+        values: [impl ToString; N],
+    ) -> Result<(Exact, usize)> {
+        self.transaction(|stream| {
+            stream.skip_whitespace();
+            stream.parse_any_str_of(values)
+        })
+    }
+
+    /// Consumes any whitespace (as defined by [`char::is_whitespace`]) at the current position,
+    /// returning the [`Span`] of the skipped text, which is zero-length if there was none.
+    pub fn skip_whitespace(&mut self) -> Span {
+        self.recover_while(|stream| stream.peek_char().is_some_and(char::is_whitespace))
+    }
+
     /// Peeks at the [`ParseStream`] to see if it can parse any of the specified values.
     ///
     /// Analogue of [`ParseStream::parse_any_value_of`].
@@ -266,6 +687,11 @@ impl ParseStream {
         self.fork().parse_any_str_of(values).is_ok()
     }
 
+    /// Analogue of [`ParseStream::parse_any_str_of_ws`].
+    pub fn peek_any_str_of_ws<const N: usize>(&self, values: [impl ToString; N]) -> bool {
+        self.fork().parse_any_str_of_ws(values).is_ok()
+    }
+
     /// Analogue of [`ParseStream::parse_any_istr_of`].
     pub fn peek_any_istr_of<const N: usize>(&self, values: [impl ToString; N]) -> bool {
         self.fork().parse_any_istr_of(values).is_ok()
@@ -274,24 +700,243 @@ impl ParseStream {
     /// Returns the remaining text in the [`ParseStream`] that has not been parsed.
     ///
     /// The first character of the remaining text is the next character to be parsed.
-    pub fn remaining(&self) -> IndexedSlice {
+    pub fn remaining(&self) -> IndexedSlice<'_> {
         self.source.slice(self.position..)
     }
 
+    /// Returns up to the next `n` characters of the [`ParseStream`] without consuming them.
+    ///
+    /// Fewer than `n` characters are returned near the end of the input, so this never panics.
+    pub fn peek_slice(&self, n: usize) -> IndexedSlice<'_> {
+        let start = self.clamped_position();
+        self.source
+            .slice(start..start.saturating_add(n).min(self.source.len()))
+    }
+
     /// Cheaply clones the [`ParseStream`] creating a new one at the same position of the
     /// original that can be used to parse independently without consuming characters from the
     /// original.
     ///
-    /// This merely involves cloning a single [`Rc`] and a [`usize`], which is why it is so cheap.
+    /// This merely involves cloning a couple of [`Rc`]s and a [`usize`], which is why it is so
+    /// cheap.
+    ///
+    /// [`Diagnostic`]s emitted on the fork (see [`ParseStream::emit`]) are only carried back to
+    /// the original if the fork is committed, e.g. by [`ParseStream::transaction`].
     pub fn fork(&self) -> Self {
         self.clone()
     }
 
+    /// Records a [`Diagnostic`] in this [`ParseStream`]'s [`DiagnosticBag`] without
+    /// interrupting parsing.
+    ///
+    /// This allows a parser to report a problem, skip to a recovery point and keep going, so
+    /// that several problems can be reported at once.
+    ///
+    /// ```
+    /// use quoth::{parsable::numbers::U64, *};
+    ///
+    /// let mut stream = ParseStream::from("1, x, 3");
+    /// let mut numbers = Vec::new();
+    /// loop {
+    ///     match stream.parse::<U64>() {
+    ///         Ok(n) => numbers.push(n.value()),
+    ///         Err(err) => {
+    ///             stream.emit(err.into());
+    ///             stream.recover_to_value(",");
+    ///         }
+    ///     }
+    ///     if stream.parse_str(", ").is_err() {
+    ///         break;
+    ///     }
+    /// }
+    /// assert_eq!(numbers, [1, 3]);
+    /// assert_eq!(stream.diagnostics().len(), 1);
+    /// ```
+    pub fn emit(&mut self, diagnostic: Diagnostic) {
+        Rc::make_mut(&mut self.diagnostics).push(diagnostic);
+    }
+
+    /// Records an error [`Diagnostic`] with the specified [`Span`] and message, see
+    /// [`ParseStream::emit`].
+    pub fn error(&mut self, span: Span, message: impl ToString) {
+        self.emit(Diagnostic::error(span, message));
+    }
+
+    /// Records an error [`Diagnostic`] with the specified [`Span`] and message, and returns
+    /// an [`ErrorNode`](parsable::ErrorNode) covering the same [`Span`] so that parsing can
+    /// continue past a local failure.
+    ///
+    /// The [`ErrorNode`](parsable::ErrorNode) is typically wrapped in an error variant of an
+    /// AST node, e.g. `Expr::Error(stream.error_node(span, "expected expression"))`. See
+    /// [`ParseStream::emit`].
+    pub fn error_node(&mut self, span: Span, message: impl ToString) -> parsable::ErrorNode {
+        self.error(span.clone(), message);
+        parsable::ErrorNode::new(span)
+    }
+
+    /// Records an error [`Diagnostic`] with the specified [`Span`] and message, and returns
+    /// `T::default()` as a placeholder so that parsing can continue past a local failure.
+    ///
+    /// Unlike [`ParseStream::error_node`], the placeholder doesn't know the [`Span`] of the
+    /// invalid input, so prefer that where the span of the placeholder matters.
+    pub fn error_default<T: Default>(&mut self, span: Span, message: impl ToString) -> T {
+        self.error(span, message);
+        T::default()
+    }
+
+    /// Returns the [`DiagnosticBag`] of [`Diagnostic`]s emitted on this [`ParseStream`] so far.
+    pub fn diagnostics(&self) -> &DiagnosticBag {
+        &self.diagnostics
+    }
+
+    /// Removes and returns the [`Diagnostic`]s emitted on this [`ParseStream`] so far, leaving
+    /// its [`DiagnosticBag`] empty.
+    pub fn take_diagnostics(&mut self) -> DiagnosticBag {
+        Rc::unwrap_or_clone(core::mem::take(&mut self.diagnostics))
+    }
+
+    /// Enters one level of nesting, returning a [`DepthGuard`] that leaves it again when
+    /// dropped, or an error if the maximum depth (see [`ParseStream::set_max_depth`]) has
+    /// already been reached.
+    ///
+    /// Recursive [`Parsable`] implementations, e.g. for nested parentheses, should hold a
+    /// guard while parsing their contents, so that adversarial input nested thousands of levels
+    /// deep produces an error rather than overflowing the stack. The depth is shared with any
+    /// forks of this [`ParseStream`].
+    ///
+    /// ```
+    /// use quoth::*;
+    ///
+    /// fn parens(stream: &mut ParseStream) -> Result<usize> {
+    ///     if stream.parse_exact_char('(').is_err() {
+    ///         return Ok(0);
+    ///     }
+    ///     let _guard = stream.enter()?;
+    ///     let depth = parens(stream)? + 1;
+    ///     stream.parse_exact_char(')')?;
+    ///     Ok(depth)
+    /// }
+    ///
+    /// let mut stream = ParseStream::from("((()))");
+    /// stream.set_max_depth(3);
+    /// assert_eq!(parens(&mut stream).unwrap(), 3);
+    /// let mut stream = ParseStream::from("(((())))");
+    /// stream.set_max_depth(3);
+    /// assert!(parens(&mut stream).is_err());
+    /// ```
+    pub fn enter(&self) -> Result<DepthGuard> {
+        let depth = self.depth.0.get();
+        if depth >= self.max_depth {
+            return Err(Error::new(
+                self.current_span(),
+                "maximum nesting depth exceeded",
+            ));
+        }
+        self.depth.0.set(depth + 1);
+        Ok(DepthGuard {
+            depth: self.depth.clone(),
+        })
+    }
+
+    /// Returns the number of nesting levels currently entered via [`ParseStream::enter`].
+    pub fn depth(&self) -> usize {
+        self.depth.0.get()
+    }
+
+    /// Returns the maximum nesting depth allowed by [`ParseStream::enter`].
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Sets the maximum nesting depth allowed by [`ParseStream::enter`], which defaults to
+    /// [`DEFAULT_MAX_DEPTH`].
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.max_depth = max_depth;
+    }
+
+    /// Consumes characters until the [`ParseStream`] peeks a `T` or reaches the end of its
+    /// input, returning the [`Span`] of the skipped text.
+    ///
+    /// This is intended for resynchronizing after an error, e.g. skipping to the next
+    /// statement boundary once a [`Diagnostic`] has been [emitted](ParseStream::emit). If a
+    /// `T` can already be peeked, nothing is consumed and a zero-length [`Span`] is returned.
+    pub fn recover_to<T: Peekable>(&mut self) -> Span {
+        self.recover_while(|stream| !stream.peek::<T>())
+    }
+
+    /// Consumes characters until the [`ParseStream`] peeks the specified [`Peekable`] value or
+    /// reaches the end of its input, returning the [`Span`] of the skipped text.
+    ///
+    /// See [`ParseStream::recover_to`].
+    pub fn recover_to_value<T: Peekable + Clone>(&mut self, value: T) -> Span {
+        self.recover_while(|stream| !stream.peek_value(value.clone()))
+    }
+
+    pub(crate) fn recover_while(&mut self, mut skip: impl FnMut(&ParseStream) -> bool) -> Span {
+        let start = self.clamped_position();
+        self.position = start;
+        while self.position < self.source.len() && skip(self) {
+            self.position += 1;
+        }
+        self.span_from(start)
+    }
+
+    /// Consumes any trailing whitespace and then checks that the [`ParseStream`] is at the end
+    /// of its input, returning an error spanning the leftover text otherwise.
+    ///
+    /// The [`ParseStream`] is not advanced if this returns an error.
+    pub fn expect_end(&mut self) -> Result<()> {
+        self.transaction(|stream| {
+            let _ = stream.parse::<parsable::Optional<parsable::Whitespace>>()?;
+            if stream.peek::<parsable::Eoi>() {
+                return Ok(());
+            }
+            Err(Error::new(
+                stream.remaining_span().trim_end(),
+                "expected end of input",
+            ))
+        })
+    }
+
+    /// Runs `f` speculatively against this [`ParseStream`], committing its progress only if
+    /// it returns [`Ok`].
+    ///
+    /// The closure operates on a [`fork`](ParseStream::fork) of the stream, so if it returns
+    /// an [`Err`] (or panics) this [`ParseStream`] is left exactly as it was, no matter how far
+    /// the closure advanced before failing.
+    ///
+    /// ```
+    /// use quoth::*;
+    ///
+    /// let mut stream = ParseStream::from("let x");
+    /// let result = stream.transaction(|s| {
+    ///     s.parse_str("let ")?;
+    ///     s.parse_str("mut ")
+    /// });
+    /// assert!(result.is_err());
+    /// assert_eq!(stream.position, 0);
+    /// ```
+    pub fn transaction<T>(&mut self, f: impl FnOnce(&mut ParseStream) -> Result<T>) -> Result<T> {
+        let mut fork = self.fork();
+        let value = f(&mut fork)?;
+        *self = fork;
+        Ok(value)
+    }
+
     /// Consumes the specified number of characters from the [`ParseStream`] and returns the
     /// consumed characters as a [`Span`].
     ///
-    /// Returns an error if the [`ParseStream`] has less remaining characters than `num_chars`.
+    /// Identical to [`ParseStream::consume_chars`]; see [`ParseStream::consume_bytes`] to
+    /// consume a number of bytes instead.
     pub fn consume(&mut self, num_chars: usize) -> Result<Span> {
+        self.consume_chars(num_chars)
+    }
+
+    /// Consumes the specified number of characters (not bytes) from the [`ParseStream`] and
+    /// returns the consumed characters as a [`Span`].
+    ///
+    /// Returns an error if the [`ParseStream`] has less remaining characters than `num_chars`.
+    pub fn consume_chars(&mut self, num_chars: usize) -> Result<Span> {
         if self.remaining().len() < num_chars {
             return Err(Error::new(
                 self.remaining_span(),
@@ -303,7 +948,45 @@ impl ParseStream {
         }
         let position = self.position;
         self.position += num_chars;
-        Ok(Span::new(self.source.clone(), position..self.position))
+        Ok(self.span_from(position))
+    }
+
+    /// Consumes the specified number of bytes of UTF-8 from the [`ParseStream`] and returns
+    /// the consumed characters as a [`Span`], e.g. for a length-prefixed run of text.
+    ///
+    /// Returns an error if the [`ParseStream`] has less remaining bytes than `num_bytes`, or
+    /// if `num_bytes` would end in the middle of a multi-byte character. In either case the
+    /// [`ParseStream`] is not advanced.
+    pub fn consume_bytes(&mut self, num_bytes: usize) -> Result<Span> {
+        let remaining = self.remaining();
+        // `IndexedSlice::byte_len` panics for slices that reach the end of the input
+        let byte_len = remaining.as_str().len();
+        if byte_len < num_bytes {
+            return Err(Error::new(
+                self.remaining_span(),
+                format!("expected at least {num_bytes} more bytes, found {byte_len}"),
+            ));
+        }
+        let mut num_chars = 0;
+        let mut len = 0;
+        for c in remaining.chars() {
+            if len >= num_bytes {
+                break;
+            }
+            len += c.len_utf8();
+            num_chars += 1;
+        }
+        if len != num_bytes {
+            let span = Span::new(
+                self.source.clone(),
+                (self.position + num_chars - 1)..(self.position + num_chars),
+            );
+            return Err(Error::new(
+                span,
+                format!("{num_bytes} bytes ends in the middle of a character"),
+            ));
+        }
+        self.consume_chars(num_chars)
     }
 
     /// Consumes the remaining text in the [`ParseStream`] and returns it as a [`Span`].
@@ -317,17 +1000,44 @@ impl ParseStream {
     ///
     /// Returns an error if the [`ParseStream`] is at the end of its input.
     pub fn next_char(&self) -> Result<char> {
-        if self.remaining().is_empty() {
-            return Err(Error::new(self.current_span(), "unexpected end of input"));
-        }
-        let c = self
-            .current_span()
-            .source_text()
-            .chars()
-            .first()
-            .cloned()
-            .unwrap();
-        Ok(c)
+        self.peek_char()
+            .ok_or_else(|| Error::new(self.current_span(), "unexpected end of input"))
+    }
+
+    /// Returns the next character in the [`ParseStream`] without consuming it, or `None` if
+    /// the [`ParseStream`] is at the end of its input.
+    ///
+    /// Non-erroring analogue of [`ParseStream::next_char`].
+    pub fn peek_char(&self) -> Option<char> {
+        self.peek_nth(0)
+    }
+
+    /// Returns `true` if the next character in the [`ParseStream`] is `c`.
+    ///
+    /// Analogue of [`ParseStream::parse_exact_char`].
+    pub fn peek_char_eq(&self, c: char) -> bool {
+        self.peek_char() == Some(c)
+    }
+
+    /// Parses exactly the character `c` from the [`ParseStream`], returning its [`Span`].
+    ///
+    /// This is a cheaper alternative to [`ParseStream::parse_str`] for single-character
+    /// syntax such as `,` or `(`. The [`ParseStream`] is not advanced if this returns an error.
+    ///
+    /// Analogue of [`ParseStream::peek_char_eq`].
+    pub fn parse_exact_char(&mut self, c: char) -> Result<Span> {
+        if !self.peek_char_eq(c) {
+            return Err(Error::expected(self.current_span(), c));
+        }
+        self.consume(1)
+    }
+
+    /// Returns the character `n` characters ahead of the current position without consuming
+    /// anything, or `None` if the input ends before then.
+    ///
+    /// `peek_nth(0)` is the next character, i.e. the same as [`ParseStream::peek_char`].
+    pub fn peek_nth(&self, n: usize) -> Option<char> {
+        self.source.char_at(self.position.checked_add(n)?)
     }
 
     /// Parses the next character in the [`ParseStream`] and advances the position by one.
@@ -339,6 +1049,41 @@ impl ParseStream {
         Ok(c)
     }
 
+    /// Parses and decodes a single escape sequence, such as `n` for a newline, from the
+    /// [`ParseStream`], which should be positioned just after the backslash.
+    ///
+    /// The default [`Escapes`](parsable::Escapes) are recognized, i.e. `\n`, `\t`, `\r`, `\\`,
+    /// `\0`, `\'`, `\"`, `\xHH` (up to `\x7F`) and `\u{...}` (up to six hex digits). Use
+    /// [`ParseStream::parse_escape_with`] to recognize a different set.
+    ///
+    /// ```
+    /// use quoth::*;
+    ///
+    /// let mut stream = ParseStream::from(r"\u{1F600} \q");
+    /// stream.position = 1;
+    /// assert_eq!(stream.parse_escape().unwrap(), '😀');
+    /// stream.position = 11;
+    /// let err = stream.parse_escape().unwrap_err();
+    /// assert_eq!(err.message(), "unknown escape sequence `\\q`");
+    /// ```
+    pub fn parse_escape(&mut self) -> Result<char> {
+        self.parse_escape_with(&parsable::Escapes::default())
+    }
+
+    /// Parses and decodes a single escape sequence recognized by `escapes`, see
+    /// [`ParseStream::parse_escape`].
+    ///
+    /// Errors point at the malformed part of the sequence, such as a missing hex digit, and
+    /// the [`ParseStream`] is not advanced on error.
+    pub fn parse_escape_with(&mut self, escapes: &parsable::Escapes) -> Result<char> {
+        let start_position = self.mark();
+        let result = escapes.decode(self);
+        if result.is_err() {
+            self.position = start_position;
+        }
+        result
+    }
+
     /// If the next character of the [`ParseStream`] is a digit (0-9), returns the digit as a
     /// `u8` _without_ consuming it, otherwise returns an error.
     pub fn next_digit(&self) -> Result<u8> {
@@ -365,21 +1110,101 @@ impl ParseStream {
         Ok(digit)
     }
 
-    /// If the next character of the [`ParseStream`] is an alphabetic character (A-Z|a-z), the
-    /// position is not advanced and the character is returned, otherwise an error is returned.
-    pub fn next_alpha(&self) -> Result<char> {
-        let c = self.next_char()?;
-        if !c.is_ascii_alphabetic() {
-            return Err(Error::new(
-                self.current_span(),
-                "expected alphabetic (A-Z|a-z)",
-            ));
-        }
-        Ok(c)
+    /// Parses an optionally negative integer from the [`ParseStream`] directly into a
+    /// primitive such as [`u32`] or [`i64`], returning it along with its [`Span`].
+    ///
+    /// This is an alternative to the wrapper types in [`parsable::numbers`] for when only the
+    /// plain value is needed. Values that don't fit in `T` (including negative values for
+    /// unsigned types) are reported as errors spanning the whole number, and the
+    /// [`ParseStream`] is not advanced if this returns an error.
+    ///
+    /// ```
+    /// use quoth::*;
+    ///
+    /// let mut stream = ParseStream::from("255 256");
+    /// assert_eq!(stream.parse_int::<u8>().unwrap().0, 255);
+    /// stream.position += 1;
+    /// assert!(stream.parse_int::<u8>().is_err());
+    /// ```
+    pub fn parse_int<T: FromStr>(&mut self) -> Result<(T, Span)>
+    where
+        T::Err: Display,
+    {
+        self.transaction(|stream| {
+            let start_position = stream.position;
+            if stream.peek_char() == Some('-') {
+                stream.position += 1;
+            }
+            stream.parse_digit()?;
+            while stream.parse_digit().is_ok() {}
+            stream.parse_primitive(start_position)
+        })
     }
 
-    /// Tries to parse the next character in the [`ParseStream`] as an alphabetic character.
-    pub fn parse_alpha(&mut self) -> Result<char> {
+    /// Parses a floating point number such as `-1.5` or `2e10` from the [`ParseStream`]
+    /// directly into a primitive such as [`f64`], returning it along with its [`Span`].
+    ///
+    /// The fractional part and exponent are both optional, so integers are accepted as well.
+    /// The [`ParseStream`] is not advanced if this returns an error.
+    pub fn parse_float<T: FromStr>(&mut self) -> Result<(T, Span)>
+    where
+        T::Err: Display,
+    {
+        self.transaction(|stream| {
+            let start_position = stream.position;
+            if stream.peek_char() == Some('-') {
+                stream.position += 1;
+            }
+            stream.parse_digit()?;
+            while stream.parse_digit().is_ok() {}
+            if stream.peek_char() == Some('.')
+                && stream.peek_nth(1).is_some_and(|c| c.is_ascii_digit())
+            {
+                stream.position += 1;
+                while stream.parse_digit().is_ok() {}
+            }
+            if matches!(stream.peek_char(), Some('e' | 'E')) {
+                let mut fork = stream.fork();
+                fork.position += 1;
+                if matches!(fork.peek_char(), Some('+' | '-')) {
+                    fork.position += 1;
+                }
+                if fork.parse_digit().is_ok() {
+                    while fork.parse_digit().is_ok() {}
+                    *stream = fork;
+                }
+            }
+            stream.parse_primitive(start_position)
+        })
+    }
+
+    /// Parses the text from `start_position` up to the current position with [`FromStr`].
+    fn parse_primitive<T: FromStr>(&self, start_position: usize) -> Result<(T, Span)>
+    where
+        T::Err: Display,
+    {
+        let span = self.span_from(start_position);
+        match span.source_text().parse() {
+            Ok(value) => Ok((value, span)),
+            Err(err) => Err(Error::new(span, err)),
+        }
+    }
+
+    /// If the next character of the [`ParseStream`] is an alphabetic character (A-Z|a-z), the
+    /// position is not advanced and the character is returned, otherwise an error is returned.
+    pub fn next_alpha(&self) -> Result<char> {
+        let c = self.next_char()?;
+        if !c.is_ascii_alphabetic() {
+            return Err(Error::new(
+                self.current_span(),
+                "expected alphabetic (A-Z|a-z)",
+            ));
+        }
+        Ok(c)
+    }
+
+    /// Tries to parse the next character in the [`ParseStream`] as an alphabetic character.
+    pub fn parse_alpha(&mut self) -> Result<char> {
         let c = self.next_alpha()?;
         self.position += 1;
         Ok(c)
@@ -391,6 +1216,28 @@ impl ParseStream {
         T::peek(self)
     }
 
+    /// Returns a boolean indicating whether the [`ParseStream`] can parse an `A` followed by a
+    /// `B` at its current position, without consuming anything. Useful for LL(2)-style
+    /// lookahead.
+    ///
+    /// Unlike calling [`ParseStream::peek`] for each type, which peeks both at the current
+    /// position, `B` is peeked at wherever the `A` ends.
+    ///
+    /// ```
+    /// use quoth::{
+    ///     parsable::{numbers::U64, Newline},
+    ///     *,
+    /// };
+    ///
+    /// let stream = ParseStream::from("12\n");
+    /// assert!(stream.peek_pair::<U64, Newline>());
+    /// assert!(!stream.peek_pair::<U64, U64>());
+    /// ```
+    pub fn peek_pair<A: Parsable, B: Peekable>(&self) -> bool {
+        let mut fork = self.fork();
+        fork.parse::<A>().is_ok() && fork.peek::<B>()
+    }
+
     /// Returns a boolean indicating whether the [`ParseStream`] can parse a specific
     /// [`Peekable`] value at its current position.
     pub fn peek_value<T: Peekable>(&self, value: T) -> bool {
@@ -398,26 +1245,193 @@ impl ParseStream {
     }
 }
 
+/// Returns `true` if `c` can continue an identifier, i.e. it is alphanumeric or `_`.
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
 impl<S: Into<Source>> From<S> for ParseStream {
     fn from(value: S) -> Self {
         ParseStream {
             source: Rc::new(value.into()),
             position: 0,
+            diagnostics: Rc::default(),
+            depth: Rc::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
         }
     }
 }
 
 /// Attempts to parse the specified string into a value of type `T`.
+///
+/// Any input remaining after the `T` is ignored, so `parse::<U64>("12 garbage")` succeeds
+/// with `12`. Use [`parse_all`] to require that the whole input is consumed.
 pub fn parse<T: Parsable>(stream: impl Into<ParseStream>) -> Result<T> {
     T::parse(&mut stream.into())
 }
 
+/// Attempts to parse the specified string into a value of type `T`, requiring that nothing but
+/// whitespace remains afterwards.
+///
+/// This is like [`parse`], followed by [`ParseStream::expect_end`], so any leftover input is
+/// reported as an error spanning the leftover text.
+///
+/// ```
+/// use quoth::{parsable::numbers::U64, *};
+///
+/// assert_eq!(parse_all::<U64>("12\n").unwrap().value(), 12);
+/// let err = parse_all::<U64>("12 garbage").unwrap_err();
+/// assert_eq!(err.span().source_text(), "garbage");
+/// ```
+pub fn parse_all<T: Parsable>(stream: impl Into<ParseStream>) -> Result<T> {
+    let mut stream = stream.into();
+    let value = stream.parse()?;
+    stream.expect_end()?;
+    Ok(value)
+}
+
+/// Tries to parse each of several (possibly different) [`Parsable`] types in order from a
+/// [`ParseStream`], mapping the first one that succeeds to a common type.
+///
+/// Each alternative is written `Type => mapping`, where `mapping` is anything callable with
+/// the parsed value, such as an enum variant or a closure. Alternatives are attempted on a
+/// fork, so a failed alternative never consumes input. If none of them match, the error
+/// lists the names of all the alternatives.
+///
+/// ```
+/// use quoth::{parsable::{numbers::U64, Exact}, *};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Token {
+///     Num(u64),
+///     Word(String),
+/// }
+///
+/// let mut stream = ParseStream::from("12");
+/// let token = choose!(&mut stream,
+///     U64 => |n: U64| Token::Num(n.value()),
+///     Exact => |e: Exact| Token::Word(e.span().source_text().to_string()),
+/// );
+/// assert_eq!(token.unwrap(), Token::Num(12));
+/// ```
+#[macro_export]
+macro_rules! choose {
+    ($stream:expr, $($ty:ty => $map:expr),+ $(,)?) => {{
+        let stream: &mut $crate::ParseStream = $stream;
+        'choose: {
+            $(
+                let mut fork = stream.fork();
+                if let Ok(value) = fork.parse::<$ty>() {
+                    *stream = fork;
+                    break 'choose Ok(($map)(value));
+                }
+            )+
+            Err($crate::Error::new(
+                stream.current_span(),
+                format!(
+                    "expected one of {}",
+                    [$(concat!("`", stringify!($ty), "`")),+].join(", ")
+                ),
+            ))
+        }
+    }};
+}
+
+/// Like [`choose!`], but tries every alternative and keeps the one that consumed the most
+/// input (maximal munch), see [`ParseStream::parse_longest_of`].
+///
+/// Ties are broken in favor of the earliest alternative. If none of the alternatives match,
+/// the error lists the names of all of them.
+///
+/// ```
+/// use quoth::{parsable::{numbers::{Decimal, U64}, Exact}, *};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Token {
+///     Int(u64),
+///     Decimal(String),
+/// }
+///
+/// let mut stream = ParseStream::from("1.5");
+/// let token = choose_longest!(&mut stream,
+///     U64 => |n: U64| Token::Int(n.value()),
+///     Decimal => |d: Decimal| Token::Decimal(d.to_string()),
+/// );
+/// assert_eq!(token.unwrap(), Token::Decimal("1.5".to_string()));
+/// ```
+#[macro_export]
+macro_rules! choose_longest {
+    ($stream:expr, $($ty:ty => $map:expr),+ $(,)?) => {{
+        let stream: &mut $crate::ParseStream = $stream;
+        stream
+            .parse_longest_of([$(
+                &|stream: &mut $crate::ParseStream| stream.parse::<$ty>().map($map),
+            )+])
+            .map_err(|_| {
+                $crate::Error::new(
+                    stream.current_span(),
+                    format!(
+                        "expected one of {}",
+                        [$(concat!("`", stringify!($ty), "`")),+].join(", ")
+                    ),
+                )
+            })
+    }};
+}
+
+/// Asserts that parsing a `T` from the start of `input` succeeds and consumes exactly the
+/// `consumed` text, returning the parsed value. Intended for use in tests of a grammar.
+///
+/// With a trailing `remaining = "..."`, it also asserts that the [`ParseStream`] is left with
+/// exactly that text unconsumed. See also [`assert_round_trip`].
+///
+/// # Panics
+///
+/// Panics with the parse error, or the actual consumed or remaining text, if any of the
+/// assertions fail.
+///
+/// ```
+/// use quoth::{parsable::numbers::U64, *};
+///
+/// let value = assert_parses!(U64, "42 rest" => "42", remaining = " rest");
+/// assert_eq!(value.value(), 42);
+/// assert_parses!(U64, "7" => "7");
+/// ```
+#[macro_export]
+macro_rules! assert_parses {
+    ($ty:ty, $input:expr => $consumed:expr $(, remaining = $remaining:expr)? $(,)?) => {{
+        let input: &str = $input;
+        let mut stream = $crate::ParseStream::from(input);
+        let value = match stream.parse::<$ty>() {
+            Ok(value) => value,
+            Err(err) => panic!("failed to parse `{}` from `{}`: {}", stringify!($ty), input, err),
+        };
+        assert_eq!(
+            stream.span_from(0).source_text(),
+            $consumed,
+            "unexpected text consumed parsing `{}` from `{}`",
+            stringify!($ty),
+            input,
+        );
+        $(
+            assert_eq!(
+                stream.remaining(),
+                $remaining,
+                "unexpected text remaining after parsing `{}` from `{}`",
+                stringify!($ty),
+                input,
+            );
+        )?
+        value
+    }};
+}
+
 /// Utility function to find the common prefix between two [`str`]s.
 pub fn common_prefix(s1: impl IndexedStr, s2: impl IndexedStr) -> IndexedString {
     let mut result = String::new();
-    for (b1, b2) in s1.chars().into_iter().zip(s2.chars()) {
+    for (b1, b2) in s1.chars().iter().zip(s2.chars()) {
         if b1 == b2 {
-            result.push(*b1 as char);
+            result.push(*b1);
         } else {
             break;
         }
@@ -461,6 +1475,31 @@ pub fn common_prefix(s1: impl IndexedStr, s2: impl IndexedStr) -> IndexedString
 /// assert_eq!(parsed.span().source_text(), "where");
 /// assert_eq!(parsed.to_string(), "where");
 /// assert_eq!(stream.remaining(), " are you");
+///
+/// // provided by `ParsableExt`
+/// let parsed: Where = "WHERE".parse().unwrap();
+/// assert_eq!(parsed.to_string(), "WHERE");
+/// // provided by the blanket `Peekable` impl
+/// assert!(ParseStream::from("where").peek::<Where>());
+/// ```
+///
+/// For record-like types, [`Parsable`] can also be derived, in which case each field is parsed
+/// in declaration order and the struct's [`Span`] field is set to the join of the field spans:
+///
+/// ```
+/// use quoth::{parsable::numbers::U64, *};
+///
+/// #[derive(Clone, Debug, PartialEq, Eq, Hash, ParsableExt, Spanned, Parsable)]
+/// pub struct Arrow {
+///     from: U64,
+///     #[quoth(skip_ws, exact = "=>")]
+///     to: U64,
+///     span: Span,
+/// }
+///
+/// let parsed = parse::<Arrow>("1 => 2").unwrap();
+/// assert_eq!(parsed.to.value(), 2);
+/// assert_eq!(parsed.span().source_text(), "1 => 2");
 /// ```
 pub trait Parsable:
     Clone + Debug + PartialEq + Eq + Hash + Display + Spanned + FromStr + Peekable
@@ -492,6 +1531,53 @@ pub trait Parsable:
     fn unparse(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.span().source_text())
     }
+
+    /// Returns the output of [`Parsable::unparse`] as a [`String`].
+    fn unparse_to_string(&self) -> String {
+        Unparsed(self).to_string()
+    }
+}
+
+/// Adapts [`Parsable::unparse`] to [`Display`].
+struct Unparsed<'a, T>(&'a T);
+
+impl<T: Parsable> Display for Unparsed<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.unparse(f)
+    }
+}
+
+/// Asserts that `T` round-trips through [`Parsable::unparse`], returning the value parsed from
+/// `input`. Intended for use in tests of a grammar.
+///
+/// `input` is parsed into a `T` (see [`parse_all`]), which is unparsed and parsed a second
+/// time. The second parse must consume the whole unparsed text and must unparse to the same
+/// text again, which catches `unparse` implementations that produce text `parse` can't read
+/// back or that don't settle on a canonical form. The unparsed texts are compared rather than
+/// the values themselves, because the values carry [`Span`]s into different sources.
+///
+/// # Panics
+///
+/// Panics with the offending texts if `input` can't be parsed or the round trip fails.
+///
+/// ```
+/// use quoth::{parsable::numbers::I64, *};
+///
+/// let value = assert_round_trip::<I64>("+42");
+/// assert_eq!(value.value(), 42);
+/// ```
+#[track_caller]
+pub fn assert_round_trip<T: Parsable>(input: &str) -> T {
+    let value = parse_all::<T>(input).unwrap_or_else(|e| panic!("failed to parse input: {e}"));
+    let unparsed = value.unparse_to_string();
+    let reparsed = parse_all::<T>(unparsed.as_str())
+        .unwrap_or_else(|e| panic!("failed to re-parse unparsed text `{unparsed}`: {e}"));
+    let reunparsed = reparsed.unparse_to_string();
+    assert_eq!(
+        unparsed, reunparsed,
+        "unparsing is not idempotent for input `{input}`"
+    );
+    value
 }
 
 impl<T: Parsable> Peekable for T {
@@ -565,7 +1651,7 @@ impl Peekable for &IndexedString {
     }
 }
 
-impl<'a> Peekable for IndexedSlice<'a> {
+impl Peekable for IndexedSlice<'_> {
     fn peek(_: &ParseStream) -> bool {
         true
     }
@@ -585,10 +1671,42 @@ impl<'a> Peekable for &'a IndexedSlice<'a> {
     }
 }
 
+/// The maximum number of compiled patterns kept in the per-thread regex cache before it is
+/// cleared.
+const REGEX_CACHE_CAPACITY: usize = 256;
+
+thread_local! {
+    static REGEX_CACHE: RefCell<HashMap<String, Regex>> = RefCell::new(HashMap::new());
+}
+
+/// Compiles the given pattern, reusing a previous compilation of the same pattern on this
+/// thread if there is one.
+fn compile_cached(pattern: &str) -> core::result::Result<Regex, regex::Error> {
+    REGEX_CACHE.with(|cache| {
+        if let Some(regex) = cache.borrow().get(pattern) {
+            return Ok(regex.clone());
+        }
+        let regex = Regex::new(pattern)?;
+        let mut cache = cache.borrow_mut();
+        if cache.len() >= REGEX_CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(pattern.to_string(), regex.clone());
+        Ok(regex)
+    })
+}
+
 /// Generic over types that can be used to create a Regex
+///
+/// Patterns given as `&str` or [`String`] are compiled at most once per thread: compiled
+/// [`Regex`]es are cached by pattern (up to a fixed number of distinct patterns), and cloning
+/// a cached [`Regex`] is cheap.
 pub trait Pattern: Sized {
-    /// Tries to derive a [`Regex`] from the underlying value, panicking if the underlying
-    /// value is not valid regex syntax.
+    /// Tries to derive a [`Regex`] from the underlying value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying value is not valid regex syntax.
     fn to_regex(self) -> Regex {
         self.try_to_regex().unwrap()
     }
@@ -604,7 +1722,7 @@ impl Pattern for Regex {
     }
 }
 
-impl<'a> Pattern for &'a Regex {
+impl Pattern for &Regex {
     fn try_to_regex(self) -> core::result::Result<Regex, regex::Error> {
         Ok(self.clone())
     }
@@ -612,16 +1730,101 @@ impl<'a> Pattern for &'a Regex {
 
 impl Pattern for &str {
     fn try_to_regex(self) -> core::result::Result<Regex, regex::Error> {
-        Regex::new(self)
+        compile_cached(self)
     }
 }
 
 impl Pattern for String {
     fn try_to_regex(self) -> core::result::Result<Regex, regex::Error> {
-        Regex::new(&self)
+        compile_cached(&self)
     }
 }
 
+/// A regex pattern along with options that are applied to it before it is compiled, usable
+/// anywhere a [`Pattern`] is accepted.
+///
+/// ```
+/// use quoth::*;
+///
+/// let mut stream = ParseStream::from("SELECT *");
+/// let keyword = RegexOptions::new("select").case_insensitive(true).anchored(true);
+/// assert_eq!(stream.parse_regex(keyword).unwrap().span().source_text(), "SELECT");
+/// ```
+///
+/// Like patterns given as strings, the resulting [`Regex`] is cached, keyed by the pattern as
+/// rewritten by these options.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct RegexOptions {
+    pattern: String,
+    case_insensitive: bool,
+    anchored: bool,
+}
+
+impl RegexOptions {
+    /// Creates a new [`RegexOptions`] for the given pattern with all options disabled.
+    pub fn new(pattern: impl ToString) -> RegexOptions {
+        RegexOptions {
+            pattern: pattern.to_string(),
+            case_insensitive: false,
+            anchored: false,
+        }
+    }
+
+    /// Sets whether the pattern should match letters regardless of case, equivalent to an
+    /// inline `(?i)` flag.
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> RegexOptions {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// Sets whether the pattern should be anchored to the start of the text with `\A`.
+    ///
+    /// [`ParseStream::parse_regex`] already requires matches to start at the current
+    /// position, but an anchored pattern fails as soon as the first character doesn't match
+    /// rather than searching the rest of the input for a match that would then be rejected.
+    pub fn anchored(mut self, anchored: bool) -> RegexOptions {
+        self.anchored = anchored;
+        self
+    }
+
+    /// Returns the pattern with these options applied, as it will be compiled.
+    pub fn to_pattern_string(&self) -> String {
+        let flags = if self.case_insensitive { "(?i)" } else { "" };
+        match self.anchored {
+            true => format!("{flags}\\A(?:{})", self.pattern),
+            false => format!("{flags}{}", self.pattern),
+        }
+    }
+}
+
+impl Pattern for RegexOptions {
+    fn try_to_regex(self) -> core::result::Result<Regex, regex::Error> {
+        compile_cached(&self.to_pattern_string())
+    }
+}
+
+impl Pattern for &RegexOptions {
+    fn try_to_regex(self) -> core::result::Result<Regex, regex::Error> {
+        compile_cached(&self.to_pattern_string())
+    }
+}
+
+#[test]
+fn test_error_with_note() {
+    let mut stream = ParseStream::from("a = 1; a = 2;");
+    let first = stream.parse_str("a").unwrap().span();
+    stream.position = 7;
+    let second = stream.parse_str("a").unwrap().span();
+    let e = Error::new(second, "duplicate key")
+        .with_note(first, "defined here")
+        .with_help(stream.current_span(), "remove this assignment");
+    assert_eq!(e.message(), "duplicate key");
+    assert_eq!(e.children().len(), 2);
+    assert_eq!(e.children()[0].level(), DiagnosticLevel::Note);
+    assert_eq!(e.children()[0].span().byte_range(), &(0..1));
+    assert_eq!(e.children()[1].level(), DiagnosticLevel::Help);
+}
+
 #[test]
 fn test_parse_digit() {
     let mut stream = ParseStream::from("0183718947");
@@ -648,7 +1851,7 @@ fn test_peeking() {
     assert!(stream.peek::<String>());
     assert!(stream.peek::<&str>());
     assert!(stream.peek::<&String>());
-    assert_eq!(stream.peek::<Nothing>(), false);
+    assert!(!stream.peek::<Nothing>());
     assert!(stream.peek::<Everything>());
     assert_eq!(
         stream.parse_value(Exact::from("hey ")).unwrap().to_string(),
@@ -720,9 +1923,748 @@ fn test_multibyte_parsing() {
     let mut stream = ParseStream::from("你好, 世界");
     assert!(stream.peek_value("你"));
     let parsed = stream.parse_istr("你好").unwrap();
-    println!("parsed: |{}|", parsed.to_string());
+    println!("parsed: |{parsed}|");
     println!("remaining: |{}|", stream.remaining());
     assert_eq!(parsed.to_string(), "你好");
     assert_ne!(stream.source().len(), stream.source().byte_len());
     assert!(stream.peek_value(","));
 }
+
+#[test]
+fn test_multibyte_regex_parsing() {
+    let mut stream = ParseStream::from("héllo wörld");
+    let parsed = stream.parse_regex(r"\w+").unwrap();
+    assert_eq!(parsed.span().source_text(), "héllo");
+    assert_eq!(stream.position, 5);
+    assert_eq!(stream.parse_char().unwrap(), ' ');
+    assert_eq!(
+        stream.parse_regex(r"\w+").unwrap().span().byte_range(),
+        &(6..11)
+    );
+    assert!(stream.remaining().is_empty());
+}
+
+#[test]
+fn test_derive_parsable_struct() {
+    use crate as quoth;
+    use parsable::numbers::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, ParsableExt, Spanned, Parsable)]
+    struct Assignment {
+        #[quoth(skip_ws)]
+        key: U64,
+        #[quoth(skip_ws, exact = "=")]
+        value: U64,
+        span: Span,
+    }
+
+    let mut stream = ParseStream::from("  1 = 42;");
+    let parsed = stream.parse::<Assignment>().unwrap();
+    assert_eq!(parsed.key.value(), 1);
+    assert_eq!(parsed.value.value(), 42);
+    assert_eq!(parsed.span().source_text(), "1 = 42");
+    assert_eq!(stream.remaining(), ";");
+    let mut stream = ParseStream::from("1=42");
+    let parsed = stream.parse::<Assignment>().unwrap();
+    assert_eq!(parsed.span().source_text(), "1=42");
+    let mut stream = ParseStream::from("1 : 42");
+    let e = stream.parse::<Assignment>().unwrap_err();
+    assert!(e.message().contains("expected `=`"));
+    assert_eq!(stream.position, 0);
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, ParsableExt, Spanned, Parsable)]
+    struct Pair(U64, #[quoth(exact = ",")] I64, Span);
+
+    let parsed: Pair = "7,-3 and more".parse().unwrap();
+    assert_eq!(parsed.0.value(), 7);
+    assert_eq!(parsed.1.value(), -3);
+    assert_eq!(parsed.span().source_text(), "7,-3");
+    let mut stream = ParseStream::from("7;-3");
+    let e = stream.parse::<Pair>().unwrap_err();
+    assert!(e.message().contains("expected `,`"));
+    assert_eq!(stream.position, 0);
+}
+
+#[test]
+fn test_derive_parsable_enum() {
+    use crate as quoth;
+    use parsable::numbers::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, ParsableExt, Spanned, Parsable)]
+    enum Value {
+        #[quoth(keyword = "true")]
+        True(Span),
+        #[quoth(keyword = "false")]
+        False {
+            keyword: Exact,
+        },
+        #[quoth(keyword = "null")]
+        Null,
+        Decimal(StrictDecimal),
+        Int(I64),
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, ParsableExt, Spanned, Parsable)]
+    struct Stmt {
+        value: Value,
+        #[quoth(skip_ws)]
+        count: I64,
+        span: Span,
+    }
+
+    let mut stream = ParseStream::from("true false");
+    let parsed = stream.parse::<Value>().unwrap();
+    assert!(matches!(parsed, Value::True(_)));
+    assert_eq!(parsed.span().byte_range(), &(0..4));
+    assert_eq!(stream.remaining(), " false");
+    stream.skip_whitespace();
+    let parsed = stream.parse::<Value>().unwrap();
+    assert!(matches!(parsed, Value::False { .. }));
+    assert_eq!(parsed.span().source_text(), "false");
+    assert_eq!(parsed.to_string(), "false");
+    let parsed: Value = "-12.5".parse().unwrap();
+    assert!(matches!(parsed, Value::Decimal(_)));
+    assert_eq!(parsed.span().source_text(), "-12.5");
+    assert_eq!(parsed.to_string(), "-12.5");
+    let parsed: Value = "-12".parse().unwrap();
+    assert!(matches!(parsed, Value::Int(_)));
+    assert_eq!(parsed.span().source_text(), "-12");
+    let mut stream = ParseStream::from("null;");
+    let parsed = stream.parse::<Value>().unwrap();
+    assert_eq!(parsed, Value::Null);
+    assert!(parsed.span().is_blank());
+    assert_eq!(parsed.to_string(), "null");
+    assert_eq!(stream.remaining(), ";");
+    assert!(ParseStream::from("nullable").parse::<Value>().is_err());
+    let parsed: Stmt = "true 5".parse().unwrap();
+    assert_eq!(parsed.span().source_text(), "true 5");
+    assert_eq!(parsed.value.span().source_text(), "true");
+    let mut stream = ParseStream::from("trueish");
+    assert!(stream.parse::<Value>().is_err());
+    assert_eq!(stream.position, 0);
+    let mut stream = ParseStream::from("nope");
+    let e = stream.parse::<Value>().unwrap_err();
+    assert_eq!(
+        e.message(),
+        "expected one of `true`, `false`, `null`, `StrictDecimal`, `I64`"
+    );
+    assert_eq!(stream.position, 0);
+}
+
+#[test]
+fn test_derive_parsable_recursive() {
+    use crate as quoth;
+    use parsable::numbers::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, ParsableExt, Spanned, Parsable)]
+    enum Expr {
+        Neg(Box<Neg>),
+        Not(Rc<Not>),
+        Int(U64),
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, ParsableExt, Spanned, Parsable)]
+    struct Neg {
+        #[quoth(exact = "-")]
+        inner: Box<Expr>,
+        span: Span,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, ParsableExt, Spanned, Parsable)]
+    struct Not {
+        #[quoth(exact = "!")]
+        inner: Rc<Expr>,
+        span: Span,
+    }
+
+    let parsed: Expr = "-!-7 x".parse().unwrap();
+    let Expr::Neg(neg) = &parsed else {
+        panic!("expected negation");
+    };
+    assert_eq!(neg.span().source_text(), "-!-7");
+    assert!(matches!(*neg.inner, Expr::Not(_)));
+    assert_eq!(neg.inner.span().source_text(), "!-7");
+    assert_eq!(parsed.to_string(), "-!-7");
+    let e = "x".parse::<Expr>().unwrap_err();
+    assert_eq!(e.message(), "expected one of `Neg`, `Not`, `U64`");
+    let input = format!("{}1", "-".repeat(100_000));
+    let mut stream = ParseStream::from(input.as_str());
+    assert!(stream.parse::<Expr>().is_err());
+    assert_eq!(stream.position, 0);
+    assert_eq!(stream.depth(), 0);
+}
+
+#[test]
+fn test_parse_stream_transaction() {
+    let mut stream = ParseStream::from("let mut x");
+    let err = stream.transaction(|s| {
+        s.parse_str("let ")?;
+        s.parse_str("const")
+    });
+    assert!(err.is_err());
+    assert_eq!(stream.position, 0);
+    let value = stream
+        .transaction(|s| {
+            s.parse_str("let ")?;
+            s.parse_str("mut")
+        })
+        .unwrap();
+    assert_eq!(value.span().source_text(), "mut");
+    assert_eq!(stream.position, 7);
+    assert_eq!(stream.remaining(), " x");
+}
+
+#[test]
+fn test_peek_regex() {
+    let stream = ParseStream::from("123abc");
+    assert!(stream.peek_regex(r"\d+"));
+    assert!(stream.peek_regex(String::from(r"\d{3}")));
+    assert!(stream.peek_regex(Regex::new(r"\d").unwrap()));
+    assert!(!stream.peek_regex(r"[a-z]+"));
+    assert_eq!(stream.position, 0);
+}
+
+#[test]
+fn test_try_parse_regex() {
+    let mut stream = ParseStream::from("123abc");
+    assert!(stream.try_parse_regex(r"(\d+").is_err());
+    assert_eq!(stream.position, 0);
+    assert!(stream.try_parse_regex(r"[a-z]+").unwrap().is_err());
+    let digits = stream.try_parse_regex(r"\d+").unwrap().unwrap();
+    assert_eq!(digits.span().source_text(), "123");
+    assert_eq!(stream.position, 3);
+}
+
+#[test]
+fn test_regex_cache() {
+    let cached = || REGEX_CACHE.with(|cache| cache.borrow().len());
+    let before = cached();
+    let mut stream = ParseStream::from("1".repeat(1000));
+    for _ in 0..1000 {
+        stream.parse_regex(r"\d").unwrap();
+        assert!(stream.peek_regex(String::from(r"\d")) || stream.remaining().is_empty());
+    }
+    assert_eq!(cached(), before + 1);
+    assert!(stream.try_parse_regex(r"(\d").is_err());
+    assert_eq!(cached(), before + 1);
+}
+
+#[test]
+fn test_peek_char_and_nth() {
+    let mut stream = ParseStream::from("é😀=z");
+    assert_eq!(stream.peek_char(), Some('é'));
+    assert_eq!(stream.peek_nth(1), Some('😀'));
+    assert_eq!(stream.peek_nth(2), Some('='));
+    assert_eq!(stream.peek_nth(4), None);
+    assert_eq!(stream.peek_nth(usize::MAX), None);
+    stream.parse_char().unwrap();
+    stream.parse_char().unwrap();
+    assert_eq!(stream.peek_char(), Some('='));
+    assert_eq!(stream.peek_nth(1), Some('z'));
+    stream.position = 4;
+    assert_eq!(stream.peek_char(), None);
+}
+
+#[test]
+fn test_parse_stream_line_col() {
+    let mut stream = ParseStream::from("if x:\n    y\n");
+    assert_eq!(stream.line_col(), LineCol { line: 0, col: 0 });
+    assert!(stream.at_line_start());
+    stream.parse_str("if x:\n").unwrap();
+    assert_eq!(stream.line_col(), LineCol { line: 1, col: 0 });
+    assert!(stream.at_line_start());
+    stream.parse_str("    ").unwrap();
+    assert_eq!(stream.line_col(), LineCol { line: 1, col: 4 });
+    assert!(!stream.at_line_start());
+    stream.parse_str("y\n").unwrap();
+    assert_eq!(stream.line_col(), LineCol { line: 2, col: 0 });
+    assert!(stream.at_line_start());
+}
+
+#[test]
+fn test_expect_end() {
+    use parsable::numbers::U64;
+    let mut stream = ParseStream::from("12 \n\t");
+    stream.parse::<U64>().unwrap();
+    stream.expect_end().unwrap();
+    assert!(stream.remaining().is_empty());
+    let mut stream = ParseStream::from("12  x");
+    stream.parse::<U64>().unwrap();
+    let err = stream.expect_end().unwrap_err();
+    assert_eq!(err.message(), "expected end of input");
+    assert_eq!(err.span().byte_range(), &(4..5));
+    assert_eq!(stream.position, 2);
+    assert_eq!(parse_all::<U64>("42\n").unwrap().value(), 42);
+    assert!(parse_all::<U64>("42 43").is_err());
+    assert_eq!(parse::<U64>("42 43").unwrap().value(), 42);
+}
+
+#[test]
+fn test_parse_all() {
+    use parsable::numbers::U64;
+    assert_eq!(parse_all::<U64>("7").unwrap().value(), 7);
+    let err = parse_all::<U64>("7 and more \n ").unwrap_err();
+    assert_eq!(err.span().source_text(), "and more");
+    assert!(err.to_string().contains("expected end of input"));
+    assert!(parse_all::<U64>("nope").is_err());
+}
+
+#[test]
+fn test_parse_stream_seek() {
+    let mut stream = ParseStream::from("héllo");
+    stream.seek(2).unwrap();
+    assert_eq!(stream.remaining(), "llo");
+    stream.seek(5).unwrap();
+    assert!(stream.remaining().is_empty());
+    let err = stream.seek(6).unwrap_err();
+    assert!(err.message().contains("cannot seek to position 6"));
+    assert_eq!(stream.position, 5);
+    stream.reset();
+    assert_eq!(stream.position, 0);
+    assert_eq!(stream.peek_char(), Some('h'));
+}
+
+#[test]
+fn test_parse_stream_out_of_range_position() {
+    let mut stream = ParseStream::from("abc");
+    stream.position = 10;
+    assert!(stream.remaining().is_empty());
+    assert_eq!(stream.current_span().byte_range(), &(3..3));
+    assert_eq!(stream.current_span().source_text(), "");
+    assert_eq!(stream.remaining_span().source_text(), "");
+    assert!(stream.next_char().is_err());
+    assert!(stream.parse_str("a").is_err());
+    assert!(stream.parse::<parsable::Eoi>().is_ok());
+    stream.position = usize::MAX;
+    assert_eq!(stream.current_span().byte_range(), &(3..3));
+    assert_eq!(stream.line_col(), LineCol { line: 0, col: 3 });
+}
+
+#[test]
+fn test_parse_stream_emit() {
+    let mut stream = ParseStream::from("b a");
+    stream.error(Span::new(stream.source().clone(), 2..3), "second");
+    let mut fork = stream.fork();
+    fork.error(Span::new(stream.source().clone(), 0..1), "discarded");
+    assert_eq!(fork.diagnostics().len(), 2);
+    assert_eq!(stream.diagnostics().len(), 1);
+    let _ = stream.transaction(|s| {
+        s.emit(Diagnostic::warning(
+            Span::new(s.source().clone(), 0..1),
+            "first",
+        ));
+        Ok(())
+    });
+    assert!(stream.diagnostics().has_errors());
+    let messages: Vec<_> = stream
+        .take_diagnostics()
+        .into_sorted()
+        .into_iter()
+        .map(|d| d.message().to_string())
+        .collect();
+    assert_eq!(messages, ["first", "second"]);
+    assert!(stream.diagnostics().is_empty());
+}
+
+#[test]
+fn test_parse_stream_recover_to() {
+    let mut stream = ParseStream::from("let x = ?!; let y");
+    stream.position = 8;
+    assert_eq!(stream.recover_to_value(";").source_text(), "?!");
+    assert_eq!(stream.remaining(), "; let y");
+    assert_eq!(stream.recover_to_value(";").source_text(), "");
+    assert_eq!(stream.position, 10);
+    stream.position += 1;
+    assert_eq!(
+        stream.recover_to::<parsable::Newline>().source_text(),
+        " let y"
+    );
+    assert_eq!(stream.remaining(), "");
+    stream.position = 100;
+    assert_eq!(
+        stream.recover_to::<parsable::Newline>().byte_range(),
+        &(17..17)
+    );
+}
+
+#[test]
+fn test_parse_stream_keyword() {
+    let mut stream = ParseStream::from("foreign for_ever for(x) for");
+    assert!(stream.peek_str("for"));
+    assert!(!stream.peek_keyword("for"));
+    let err = stream.parse_keyword("for").unwrap_err();
+    assert_eq!(err.span().source_text(), "foreign");
+    assert_eq!(err.message(), "expected keyword `for`");
+    assert_eq!(stream.position, 0);
+    stream.position = 8;
+    assert!(!stream.peek_keyword("for"));
+    stream.position = 17;
+    assert_eq!(
+        stream.parse_keyword("for").unwrap().span().source_text(),
+        "for"
+    );
+    assert_eq!(stream.remaining(), "(x) for");
+    stream.position = 24;
+    assert!(stream.peek_keyword("for"));
+    assert!(stream.parse_keyword("for").is_ok());
+    let mut stream = ParseStream::from("while");
+    assert!(stream.parse_keyword("for").is_err());
+    assert_eq!(stream.position, 0);
+}
+
+#[test]
+fn test_regex_options() {
+    let options = RegexOptions::new("a|b");
+    assert_eq!(options.to_pattern_string(), "a|b");
+    let options = options.case_insensitive(true).anchored(true);
+    assert_eq!(options.to_pattern_string(), r"(?i)\A(?:a|b)");
+    let mut stream = ParseStream::from("Bat");
+    assert!(stream.peek_regex(&options));
+    assert!(!stream.peek_regex("a|b"));
+    assert_eq!(
+        stream.parse_regex(&options).unwrap().span().source_text(),
+        "B"
+    );
+    assert!(stream.parse_regex(&options).is_ok());
+    let err = stream.parse_regex(&options).unwrap_err();
+    assert_eq!(err.message(), r"expected match for `(?i)\A(?:a|b)`");
+    assert!(RegexOptions::new("(").try_to_regex().is_err());
+}
+
+#[test]
+fn test_parse_stream_parse_map() {
+    use parsable::numbers::U64;
+
+    let mut stream = ParseStream::from("7 300");
+    assert_eq!(stream.parse_map(|n: U64| n.value() + 1).unwrap(), 8);
+    stream.position += 1;
+    let even = |n: U64| match n.value() % 2 {
+        0 => Ok(n.value()),
+        _ => Err(Error::new(n.span(), "expected an even number")),
+    };
+    assert_eq!(stream.parse_try_map(even).unwrap(), 300);
+    let mut stream = ParseStream::from("301");
+    let err = stream.parse_try_map(even).unwrap_err();
+    assert_eq!(err.message(), "expected an even number");
+    assert_eq!(err.span().source_text(), "301");
+    assert_eq!(stream.position, 0);
+}
+
+#[test]
+fn test_parse_stream_expect() {
+    use crate::parsable::numbers::U64;
+    let mut stream = ParseStream::from("12 -5");
+    assert_eq!(stream.expect::<U64>("a count").unwrap().value(), 12);
+    stream.position = 3;
+    let err = stream.expect::<U64>("a count").unwrap_err();
+    assert_eq!(err.message(), "expected a count");
+    assert_eq!(err.span().source_text(), "-");
+    assert_eq!(err.children().len(), 1);
+    assert_eq!(err.children()[0].level(), DiagnosticLevel::Error);
+    assert_eq!(stream.position, 3);
+}
+
+#[test]
+fn test_parse_stream_consume_multibyte() {
+    let mut stream = ParseStream::from("é😀z");
+    assert_eq!(stream.consume(2).unwrap().source_text(), "é😀");
+    assert_eq!(stream.position, 2);
+    assert_eq!(stream.remaining(), "z");
+    assert!(stream.consume(2).is_err());
+    assert_eq!(stream.next_char().unwrap(), 'z');
+}
+
+#[test]
+fn test_parse_stream_next_char_multibyte() {
+    let mut stream = ParseStream::from("é😀");
+    assert_eq!(stream.next_char().unwrap(), 'é');
+    assert_eq!(stream.parse_char().unwrap(), 'é');
+    assert_eq!(stream.parse_char().unwrap(), '😀');
+    let err = stream.next_char().unwrap_err();
+    assert_eq!(err.message(), "unexpected end of input");
+    stream.position = 10;
+    assert!(stream.parse_char().is_err());
+}
+
+#[test]
+fn test_parse_stream_consume_chars_and_bytes() {
+    let mut stream = ParseStream::from("héllo wörld");
+    assert_eq!(stream.consume_chars(2).unwrap().source_text(), "hé");
+    assert_eq!(stream.consume_bytes(4).unwrap().source_text(), "llo ");
+    assert_eq!(stream.position, 6);
+    let err = stream.consume_bytes(2).unwrap_err();
+    assert_eq!(err.message(), "2 bytes ends in the middle of a character");
+    assert_eq!(err.span().source_text(), "ö");
+    assert_eq!(stream.position, 6);
+    let err = stream.consume_bytes(7).unwrap_err();
+    assert_eq!(err.message(), "expected at least 7 more bytes, found 6");
+    assert_eq!(stream.consume_bytes(3).unwrap().source_text(), "wö");
+    let err = stream.consume_chars(4).unwrap_err();
+    assert_eq!(
+        err.message(),
+        "expected at least 4 more characters, found 3"
+    );
+    assert_eq!(stream.consume(3).unwrap().source_text(), "rld");
+    assert!(stream.consume_bytes(0).unwrap().is_blank());
+}
+
+#[test]
+fn test_parse_stream_peek_pair() {
+    use parsable::{numbers::U64, *};
+    let mut stream = ParseStream::from("1 2\n3");
+    assert!(stream.peek_pair::<U64, Whitespace>());
+    assert!(!stream.peek_pair::<U64, Newline>());
+    assert!(!stream.peek_pair::<Newline, U64>());
+    assert_eq!(stream.position, 0);
+    stream.position = 2;
+    assert!(stream.peek_pair::<U64, Newline>());
+    assert!(stream.peek_pair::<U64, Optional<U64>>());
+    assert_eq!(stream.position, 2);
+}
+
+#[test]
+fn test_parse_stream_parse_if_and_when() {
+    use parsable::{numbers::U64, *};
+    let mut stream = ParseStream::from("12\n34");
+    assert_eq!(stream.parse_if::<U64>(false).unwrap(), Optional::None);
+    assert_eq!(stream.position, 0);
+    let parsed = stream.parse_if::<U64>(true).unwrap();
+    assert_eq!(Option::from(parsed).map(|n: U64| n.value()), Some(12));
+    assert!(stream.parse_when::<U64, U64>().unwrap().is_none());
+    assert_eq!(stream.position, 2);
+    assert!(stream.parse_when::<Newline, Newline>().unwrap().is_some());
+    assert!(stream.parse_if::<Newline>(true).is_err());
+}
+
+#[test]
+fn test_error_into_diagnostic() {
+    let mut stream = ParseStream::from("x");
+    let err = stream.parse_str("y").unwrap_err();
+    let warning = err.clone().with_level(DiagnosticLevel::Warning);
+    assert_eq!(warning.level(), DiagnosticLevel::Warning);
+    assert_eq!(warning.message(), err.message());
+    stream.emit(warning);
+    assert!(!stream.diagnostics().has_errors());
+    let diagnostic = err.into_diagnostic();
+    assert_eq!(diagnostic.level(), DiagnosticLevel::Error);
+    assert_eq!(diagnostic.message(), "expected `y`");
+}
+
+#[test]
+fn test_choose() {
+    use parsable::{numbers::*, Whitespace};
+
+    #[derive(Debug, PartialEq)]
+    enum Item {
+        Int(I64),
+        Space(Whitespace),
+    }
+
+    let mut stream = ParseStream::from("-5 x");
+    let item = choose!(&mut stream, I64 => Item::Int, Whitespace => Item::Space).unwrap();
+    assert!(matches!(item, Item::Int(i) if i.value() == -5));
+    let item = choose!(&mut stream, I64 => Item::Int, Whitespace => Item::Space).unwrap();
+    assert!(matches!(item, Item::Space(_)));
+    let err = choose!(&mut stream, I64 => Item::Int, Whitespace => Item::Space).unwrap_err();
+    assert_eq!(err.message(), "expected one of `I64`, `Whitespace`");
+    assert_eq!(stream.position, 3);
+}
+
+#[test]
+fn test_parse_longest_of() {
+    use parsable::{numbers::*, Whitespace};
+
+    let mut stream = ParseStream::from("12.5 x");
+    let parsed = choose_longest!(&mut stream,
+        U64 => |n: U64| n.to_string(),
+        Decimal => |d: Decimal| d.to_string(),
+        I64 => |n: I64| n.to_string(),
+    );
+    assert_eq!(parsed.unwrap(), "12.5");
+    assert_eq!(stream.remaining(), " x");
+    let parsed = choose_longest!(&mut stream, Whitespace => |_| 1, Whitespace => |_| 2);
+    assert_eq!(parsed.unwrap(), 1);
+    let err = choose_longest!(&mut stream, U64 => |_| (), Whitespace => |_| ()).unwrap_err();
+    assert_eq!(err.message(), "expected one of `U64`, `Whitespace`");
+    assert_eq!(stream.position, 5);
+
+    let mut stream = ParseStream::from("abd");
+    let abc = |stream: &mut ParseStream| stream.parse_str("abc").map(|_| ());
+    let x = |stream: &mut ParseStream| stream.parse_str("x").map(|_| ());
+    let err = stream.parse_longest_of([&x, &abc]).unwrap_err();
+    assert_eq!(err.message(), "expected `c`");
+    assert_eq!(stream.position, 0);
+    let err = stream.parse_longest_of::<(), 0>([]).unwrap_err();
+    assert_eq!(err.message(), "no alternatives to parse");
+}
+
+#[test]
+fn test_assert_parses() {
+    use parsable::{numbers::U64, Whitespace};
+
+    let value = assert_parses!(U64, "12 x" => "12", remaining = " x");
+    assert_eq!(value.value(), 12);
+    assert_parses!(Whitespace, " \n x" => " \n ", remaining = "x");
+    let result = std::panic::catch_unwind(|| assert_parses!(U64, "12 x" => "1"));
+    assert!(result.is_err());
+    let result = std::panic::catch_unwind(|| assert_parses!(U64, "x" => ""));
+    assert!(result.is_err());
+    let result = std::panic::catch_unwind(|| assert_parses!(U64, "12 x" => "12", remaining = "x"));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_error_from_io_and_regex() {
+    fn read_and_parse(path: &str) -> Result<Exact> {
+        let source = Source::from_file(path)?;
+        ParseStream::from(source).parse_str("x")
+    }
+
+    let err = read_and_parse("/nonexistent/quoth/file").unwrap_err();
+    assert_eq!(err.span(), Span::blank());
+    assert!(!err.message().is_empty());
+
+    fn parse_pattern(pattern: &str) -> Result<Exact> {
+        ParseStream::from("abc").try_parse_regex(pattern)?
+    }
+
+    assert!(parse_pattern("a").is_ok());
+    let err = parse_pattern("(").unwrap_err();
+    assert!(err.message().contains("unclosed group"));
+}
+
+#[test]
+fn test_parse_stream_parse_int() {
+    let mut stream = ParseStream::from("-128 42 -1 9999999999");
+    let (value, span) = stream.parse_int::<i8>().unwrap();
+    assert_eq!(value, -128);
+    assert_eq!(span.source_text(), "-128");
+    stream.position += 1;
+    assert_eq!(stream.parse_int::<u32>().unwrap().0, 42);
+    stream.position += 1;
+    assert!(stream.parse_int::<u64>().is_err());
+    assert_eq!(stream.position, 8);
+    stream.position += 3;
+    let err = stream.parse_int::<u32>().unwrap_err();
+    assert_eq!(err.span().source_text(), "9999999999");
+    assert!(err.message().contains("too large"));
+    assert_eq!(stream.parse_int::<u64>().unwrap().0, 9999999999);
+    assert!(ParseStream::from("-x").parse_int::<i32>().is_err());
+}
+
+#[test]
+fn test_parse_stream_parse_float() {
+    let mut stream = ParseStream::from("-1.5 2e3 7 1.x 4e+ 5E-1");
+    assert_eq!(stream.parse_float::<f64>().unwrap().0, -1.5);
+    stream.position += 1;
+    assert_eq!(stream.parse_float::<f64>().unwrap().0, 2000.0);
+    stream.position += 1;
+    assert_eq!(stream.parse_float::<f32>().unwrap().0, 7.0);
+    stream.position += 1;
+    let (value, span) = stream.parse_float::<f64>().unwrap();
+    assert_eq!((value, span.source_text().as_str()), (1.0, "1"));
+    stream.position += 3;
+    assert_eq!(stream.parse_float::<f64>().unwrap().1.source_text(), "4");
+    stream.position += 3;
+    assert_eq!(stream.parse_float::<f64>().unwrap().0, 0.5);
+    assert!(ParseStream::from(".5").parse_float::<f64>().is_err());
+}
+
+#[test]
+fn test_parse_any_str_of_ws() {
+    let mut stream = ParseStream::from("a \n\t+  b");
+    stream.position = 1;
+    assert!(!stream.peek_any_str_of(["+", "-"]));
+    assert!(stream.peek_any_str_of_ws(["+", "-"]));
+    let (exact, i) = stream.parse_any_str_of_ws(["-", "+"]).unwrap();
+    assert_eq!((exact.span().byte_range(), i), (&(4..5), 1));
+    let err = stream.parse_any_str_of_ws(["-", "+"]).unwrap_err();
+    assert_eq!(err.span().byte_range(), &(7..8));
+    assert_eq!(stream.position, 5);
+    assert_eq!(stream.skip_whitespace().source_text(), "  ");
+    assert_eq!(stream.skip_whitespace().source_text(), "");
+}
+
+#[test]
+fn test_unparse_to_string() {
+    use crate as quoth;
+
+    #[derive(Clone, Debug, PartialEq, Eq, Hash, ParsableExt, Spanned)]
+    struct Shouty(Span);
+
+    impl Parsable for Shouty {
+        fn parse(stream: &mut ParseStream) -> Result<Self> {
+            Ok(Shouty(stream.parse_regex("[a-zA-Z]+!*")?.span()))
+        }
+
+        fn unparse(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}!", self.0.source_text())
+        }
+    }
+
+    let value = parse::<Shouty>("hey").unwrap();
+    assert_eq!(value.unparse_to_string(), "hey!");
+    assert_eq!(value.to_string(), "hey!");
+    let value = assert_round_trip::<parsable::numbers::U64>("42");
+    assert_eq!(value.unparse_to_string(), "42");
+    let result = std::panic::catch_unwind(|| assert_round_trip::<Shouty>("hey"));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_parse_stream_parse_exact_char() {
+    let mut stream = ParseStream::from("(é,");
+    assert!(stream.peek_char_eq('('));
+    assert_eq!(stream.parse_exact_char('(').unwrap().byte_range(), &(0..1));
+    let err = stream.parse_exact_char(',').unwrap_err();
+    assert_eq!(err.message(), "expected `,`");
+    assert_eq!(err.span().source_text(), "é");
+    assert_eq!(stream.position, 1);
+    stream.position = 3;
+    assert!(!stream.peek_char_eq(','));
+    assert!(stream.parse_exact_char(',').is_err());
+}
+
+#[test]
+fn test_parse_stream_enter() {
+    let mut stream = ParseStream::from("x");
+    assert_eq!(stream.max_depth(), DEFAULT_MAX_DEPTH);
+    stream.set_max_depth(2);
+    let outer = stream.enter().unwrap();
+    let fork = stream.fork();
+    let inner = fork.enter().unwrap();
+    assert_eq!(stream.depth(), 2);
+    let err = stream.enter().unwrap_err();
+    assert_eq!(err.message(), "maximum nesting depth exceeded");
+    drop(inner);
+    assert_eq!(stream.depth(), 1);
+    drop(outer);
+    assert_eq!(stream.depth(), 0);
+    assert!(stream.enter().is_ok());
+    assert_eq!(stream.depth(), 0);
+}
+
+#[test]
+fn test_parse_stream_peek_slice() {
+    let mut stream = ParseStream::from("héllo");
+    assert_eq!(stream.peek_slice(2), "hé");
+    assert_eq!(stream.peek_slice(0), "");
+    stream.position = 3;
+    assert_eq!(stream.peek_slice(10), "lo");
+    assert_eq!(stream.peek_slice(usize::MAX), "lo");
+    assert_eq!(stream.position, 3);
+    stream.position = 50;
+    assert_eq!(stream.peek_slice(1), "");
+}
+
+#[test]
+fn test_parse_stream_span_from() {
+    let mut stream = ParseStream::from("abcdef");
+    let mark = stream.mark();
+    stream.consume(3).unwrap();
+    assert_eq!(stream.span_from(mark).source_text(), "abc");
+    assert_eq!(stream.span_from(1).source_text(), "bc");
+    assert_eq!(stream.span_from(5).byte_range(), &(3..3));
+    stream.position = 10;
+    assert_eq!(stream.span_from(4).byte_range(), &(4..6));
+}
@@ -0,0 +1,293 @@
+//! Home of [`PrattParser`], for parsing expressions with operator precedence.
+
+use std::rc::Rc;
+
+use super::*;
+
+/// The associativity of an infix operator registered with [`PrattParser::infix`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Assoc {
+    /// `a - b - c` parses as `(a - b) - c`.
+    Left,
+    /// `a ^ b ^ c` parses as `a ^ (b ^ c)`.
+    Right,
+}
+
+type Atom<E> = Box<dyn Fn(&mut ParseStream, &PrattParser<E>) -> Result<E>>;
+type Unary<E> = Box<dyn FnOnce(E) -> E>;
+type Binary<E> = Box<dyn FnOnce(E, E) -> E>;
+type TokenParser<F> = Box<dyn Fn(&mut ParseStream) -> Result<F>>;
+
+/// An operator registered with a [`PrattParser`], which parses its token and returns a
+/// closure folding its operands into an expression.
+struct Operator<F> {
+    left_bp: u32,
+    right_bp: u32,
+    parse: TokenParser<F>,
+}
+
+/// Parses expressions built from atoms and prefix, infix and postfix operators, respecting
+/// the precedence and associativity of each operator.
+///
+/// Each operator is a [`Parsable`] token, such as a `+` punctuation type, along with a fold
+/// closure that combines the parsed token and its operands into an expression `E`. The fold
+/// closures also receive the joined [`Span`] of the token and its operands, so the resulting
+/// expression can carry the correct span. Atoms, i.e. everything that isn't an operator, are
+/// parsed by the closure passed to [`PrattParser::new`], which also receives the
+/// [`PrattParser`] so that it can parse nested expressions such as parenthesized ones.
+///
+/// Higher precedences bind more tightly. At each position, operators are tried in the order
+/// in which they were registered and the first one whose token parses is used, so operators
+/// that are prefixes of others (like `*` and `**`) must be registered longest first. As with
+/// the rest of quoth, any whitespace around tokens and atoms must be handled by the tokens and
+/// the atom closure themselves.
+///
+/// ```
+/// use quoth::{parsable::numbers::I64, *};
+///
+/// #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+/// struct Op<const C: char>(Span);
+///
+/// impl<const C: char> Parsable for Op<C> {
+///     fn parse(stream: &mut ParseStream) -> Result<Self> {
+///         Ok(Op(stream.parse_exact_char(C)?))
+///     }
+/// }
+///
+/// let pratt = PrattParser::new(|stream, _| stream.parse_map(|n: I64| Sp(n.value(), n.span())))
+///     .infix(1, Assoc::Left, |a, _: Op<'+'>, b, span| Sp(*a + *b, span))
+///     .infix(2, Assoc::Left, |a, _: Op<'*'>, b, span| Sp(*a * *b, span))
+///     .prefix(3, |_: Op<'-'>, a, span| Sp(-*a, span));
+/// let mut stream = ParseStream::from("1+2*-3+4;");
+/// let value = pratt.parse(&mut stream).unwrap();
+/// assert_eq!(*value, -1);
+/// assert_eq!(value.span().source_text(), "1+2*-3+4");
+/// ```
+pub struct PrattParser<E> {
+    atom: Atom<E>,
+    prefix: Vec<Operator<Unary<E>>>,
+    infix: Vec<Operator<Binary<E>>>,
+    postfix: Vec<Operator<Unary<E>>>,
+}
+
+impl<E: Spanned + 'static> PrattParser<E> {
+    /// Creates a new [`PrattParser`] with no operators, which parses atoms using `atom`.
+    pub fn new(
+        atom: impl Fn(&mut ParseStream, &PrattParser<E>) -> Result<E> + 'static,
+    ) -> PrattParser<E> {
+        PrattParser {
+            atom: Box::new(atom),
+            prefix: Vec::new(),
+            infix: Vec::new(),
+            postfix: Vec::new(),
+        }
+    }
+
+    /// Registers a prefix operator `T` with the given precedence.
+    ///
+    /// The operand extends over any infix and postfix operators of higher precedence, so a
+    /// prefix `-` of precedence 3 parses `-a * b` as `(-a) * b` when `*` has precedence 2,
+    /// and as `-(a * b)` when `*` has precedence 4.
+    pub fn prefix<T: Parsable + 'static>(
+        mut self,
+        precedence: u16,
+        fold: impl Fn(T, E, Span) -> E + 'static,
+    ) -> PrattParser<E> {
+        let fold = Rc::new(fold);
+        self.prefix.push(Operator {
+            left_bp: 0,
+            right_bp: binding_power(precedence) + 1,
+            parse: Box::new(move |stream| {
+                let token = stream.parse::<T>()?;
+                let fold = fold.clone();
+                Ok(Box::new(move |operand: E| {
+                    let span = join_spans([token.span(), operand.span()]);
+                    fold(token, operand, span)
+                }))
+            }),
+        });
+        self
+    }
+
+    /// Registers an infix operator `T` with the given precedence and associativity.
+    pub fn infix<T: Parsable + 'static>(
+        mut self,
+        precedence: u16,
+        assoc: Assoc,
+        fold: impl Fn(E, T, E, Span) -> E + 'static,
+    ) -> PrattParser<E> {
+        let fold = Rc::new(fold);
+        let bp = binding_power(precedence);
+        let (left_bp, right_bp) = match assoc {
+            Assoc::Left => (bp, bp + 1),
+            Assoc::Right => (bp + 1, bp),
+        };
+        self.infix.push(Operator {
+            left_bp,
+            right_bp,
+            parse: Box::new(move |stream| {
+                let token = stream.parse::<T>()?;
+                let fold = fold.clone();
+                Ok(Box::new(move |lhs: E, rhs: E| {
+                    let span = join_spans([lhs.span(), token.span(), rhs.span()]);
+                    fold(lhs, token, rhs, span)
+                }))
+            }),
+        });
+        self
+    }
+
+    /// Registers a postfix operator `T` with the given precedence.
+    pub fn postfix<T: Parsable + 'static>(
+        mut self,
+        precedence: u16,
+        fold: impl Fn(E, T, Span) -> E + 'static,
+    ) -> PrattParser<E> {
+        let fold = Rc::new(fold);
+        self.postfix.push(Operator {
+            left_bp: binding_power(precedence),
+            right_bp: 0,
+            parse: Box::new(move |stream| {
+                let token = stream.parse::<T>()?;
+                let fold = fold.clone();
+                Ok(Box::new(move |operand: E| {
+                    let span = join_spans([operand.span(), token.span()]);
+                    fold(operand, token, span)
+                }))
+            }),
+        });
+        self
+    }
+
+    /// Parses an expression from the [`ParseStream`], with operators of any precedence.
+    pub fn parse(&self, stream: &mut ParseStream) -> Result<E> {
+        self.parse_expr(stream, 0)
+    }
+
+    /// Parses an expression from the [`ParseStream`], stopping at the first infix or postfix
+    /// operator with a precedence lower than `min_precedence`.
+    ///
+    /// Parsing stops without error at anything that isn't an operator, so the caller decides
+    /// what may follow an expression. On error the [`ParseStream`] is not advanced.
+    pub fn parse_expr(&self, stream: &mut ParseStream, min_precedence: u16) -> Result<E> {
+        stream.transaction(|stream| self.parse_bp(stream, binding_power(min_precedence)))
+    }
+
+    fn parse_bp(&self, stream: &mut ParseStream, min_bp: u32) -> Result<E> {
+        let _guard = stream.enter()?;
+        let mut lhs = match find_operator(&self.prefix, stream) {
+            Some((op, fork, fold)) => {
+                *stream = fork;
+                fold(self.parse_bp(stream, op.right_bp)?)
+            }
+            None => (self.atom)(stream, self)?,
+        };
+        loop {
+            if let Some((op, fork, fold)) = find_operator(&self.postfix, stream) {
+                if op.left_bp < min_bp {
+                    break;
+                }
+                *stream = fork;
+                lhs = fold(lhs);
+                continue;
+            }
+            let Some((op, fork, fold)) = find_operator(&self.infix, stream) else {
+                break;
+            };
+            if op.left_bp < min_bp {
+                break;
+            }
+            *stream = fork;
+            let rhs = self.parse_bp(stream, op.right_bp)?;
+            lhs = fold(lhs, rhs);
+        }
+        Ok(lhs)
+    }
+}
+
+/// Returns the first of `ops` whose token parses at the current position, along with a fork
+/// of `stream` positioned after the token and the fold closure for the operator.
+fn find_operator<'a, F>(
+    ops: &'a [Operator<F>],
+    stream: &ParseStream,
+) -> Option<(&'a Operator<F>, ParseStream, F)> {
+    ops.iter().find_map(|op| {
+        let mut fork = stream.fork();
+        (op.parse)(&mut fork).ok().map(|fold| (op, fork, fold))
+    })
+}
+
+/// Converts a precedence into a binding power, leaving room to break ties by associativity.
+fn binding_power(precedence: u16) -> u32 {
+    2 * precedence as u32 + 1
+}
+
+/// Joins `spans` into a single [`Span`], or [`Span::blank`] if they can't be joined.
+fn join_spans<const N: usize>(spans: [Span; N]) -> Span {
+    Span::union_all(spans)
+        .ok()
+        .flatten()
+        .unwrap_or_else(Span::blank)
+}
+
+#[test]
+fn test_pratt_parser() {
+    use crate as quoth;
+    use parsable::numbers::U64;
+
+    #[derive(Clone, PartialEq, Eq, Debug, Hash, ParsableExt, Spanned)]
+    struct Op<const C: char>(Span);
+
+    impl<const C: char> Parsable for Op<C> {
+        fn parse(stream: &mut ParseStream) -> Result<Self> {
+            Ok(Op(stream.parse_exact_char(C)?))
+        }
+    }
+
+    fn atom(stream: &mut ParseStream, pratt: &PrattParser<Sp<String>>) -> Result<Sp<String>> {
+        if let Ok(open) = stream.parse::<Op<'('>>() {
+            let inner = pratt.parse(stream)?;
+            let close = stream.parse::<Op<')'>>()?;
+            return Ok(Sp(inner.0, join_spans([open.0, close.0])));
+        }
+        stream.parse_map(|n: U64| Sp(n.to_string(), n.span()))
+    }
+
+    let pratt = PrattParser::new(atom)
+        .infix(1, Assoc::Left, |a, _: Op<'-'>, b, span| {
+            Sp(format!("({} - {})", *a, *b), span)
+        })
+        .infix(2, Assoc::Left, |a, _: Op<'*'>, b, span| {
+            Sp(format!("({} * {})", *a, *b), span)
+        })
+        .infix(4, Assoc::Right, |a, _: Op<'^'>, b, span| {
+            Sp(format!("({} ^ {})", *a, *b), span)
+        })
+        .prefix(3, |_: Op<'-'>, a, span| Sp(format!("(-{})", *a), span))
+        .postfix(5, |a, _: Op<'!'>, span| Sp(format!("({}!)", *a), span));
+    let parse = |input: &str| {
+        let mut stream = ParseStream::from(input);
+        let expr = pratt.parse(&mut stream).map(|expr| {
+            assert_eq!(expr.span().source_text(), input.trim_end_matches(';'));
+            expr.0
+        });
+        (expr, stream.position)
+    };
+    assert_eq!(parse("1-2-3").0.unwrap(), "((1 - 2) - 3)");
+    assert_eq!(parse("2^3^4").0.unwrap(), "(2 ^ (3 ^ 4))");
+    assert_eq!(parse("1-2*3").0.unwrap(), "(1 - (2 * 3))");
+    assert_eq!(parse("-1*2").0.unwrap(), "((-1) * 2)");
+    assert_eq!(parse("-2^3").0.unwrap(), "(-(2 ^ 3))");
+    assert_eq!(parse("-3!").0.unwrap(), "(-(3!))");
+    assert_eq!(parse("--1").0.unwrap(), "(-(-1))");
+    assert_eq!(parse("(1-2)*3!").0.unwrap(), "((1 - 2) * (3!))");
+    assert_eq!(parse("1*2;"), (Ok("(1 * 2)".to_string()), 3));
+    let (result, position) = parse("1*(2-");
+    assert_eq!(result.unwrap_err().message(), "expected digit");
+    assert_eq!(position, 0);
+
+    let mut stream = ParseStream::from("1-2*3");
+    let expr = pratt.parse_expr(&mut stream, 2).unwrap();
+    assert_eq!(*expr, "1");
+    assert_eq!(stream.remaining(), "-2*3");
+}
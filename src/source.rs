@@ -3,6 +3,8 @@
 use super::*;
 
 use std::{
+    cmp::Ordering,
+    io::Read,
     ops::Deref,
     path::{Path, PathBuf},
 };
@@ -12,41 +14,121 @@ use std::{
 pub struct Source {
     text: IndexedString,
     path: Option<PathBuf>,
+    name: Option<String>,
+    /// The (character) offset at which each line of `text` starts, in ascending order.
+    line_starts: Vec<usize>,
 }
 
 impl Source {
+    fn new(text: IndexedString, path: Option<PathBuf>) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(
+                text.chars()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| **c == '\n')
+                    .map(|(i, _)| i + 1),
+            )
+            .collect();
+        Source {
+            text,
+            path,
+            name: None,
+            line_starts,
+        }
+    }
+
     /// Returns the underlying text of this [`Source`], with original formatting.
-    pub fn source_text(&self) -> IndexedSlice {
+    pub fn source_text(&self) -> IndexedSlice<'_> {
         self.text.as_slice()
     }
 
     /// Returns the path of the file that this [`Source`] was read from, if it was read from a file.
     pub fn source_path(&self) -> Option<&Path> {
-        self.path.as_ref().map(|path| path.as_path())
+        self.path.as_deref()
     }
 
     /// Creates a new [`Source`] from a string.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(string: impl AsRef<str>) -> Self {
-        Source {
-            text: IndexedString::from_str(string.as_ref()),
-            path: None,
-        }
+        Source::new(IndexedString::from_str(string.as_ref()), None)
     }
 
     /// Creates a new [`Source`] from an [`IndexedString`].
     pub fn from_indexed_string(text: IndexedString) -> Self {
-        Source { text, path: None }
+        Source::new(text, None)
+    }
+
+    /// Creates a new [`Source`] from a string, reporting the given path in diagnostics.
+    ///
+    /// Unlike [`Source::from_file`], the filesystem is never touched, so this suits virtual
+    /// files such as unsaved editor buffers or documents identified by a URI.
+    pub fn from_text_and_path(text: impl AsRef<str>, path: impl AsRef<Path>) -> Self {
+        Source::new(
+            IndexedString::from_str(text.as_ref()),
+            Some(path.as_ref().to_path_buf()),
+        )
     }
 
     /// Reads the contents of a file and returns a [`Source`] with the file's text.
     ///
     /// Since no parsing is done at this stage, only IO or encoding errors will be returned,
     /// regardless of the validity of the syntax in the file.
+    ///
+    /// The text read from the file is moved into the [`Source`] rather than copied, so peak
+    /// memory use is that of the resulting [`IndexedString`]. Note that an [`IndexedString`]
+    /// indexes every character up front, using several times the size of the file in memory.
     pub fn from_file(path: impl AsRef<Path>) -> core::result::Result<Self, std::io::Error> {
-        std::fs::read_to_string(path.as_ref()).map(|text| Source {
-            text: IndexedString::from(&text),
-            path: Some(path.as_ref().to_path_buf()),
-        })
+        std::fs::read_to_string(path.as_ref())
+            .map(|text| Source::new(IndexedString::from(text), Some(path.as_ref().to_path_buf())))
+    }
+
+    /// Returns the line and column of the character at the given index within this [`Source`].
+    ///
+    /// Indices past the end of the [`Source`] are clamped to its length. Line starts are
+    /// computed once when the [`Source`] is created, so this is a binary search.
+    pub fn line_col(&self, char_index: usize) -> LineCol {
+        let index = char_index.min(self.text.len());
+        let line = self.line_starts.partition_point(|start| *start <= index) - 1;
+        LineCol {
+            line,
+            col: index - self.line_starts[line],
+        }
+    }
+
+    /// Returns the character index at which the given (zero-indexed) line starts, if the
+    /// [`Source`] has that many lines.
+    pub(crate) fn line_start(&self, line: usize) -> Option<usize> {
+        self.line_starts.get(line).copied()
+    }
+
+    /// Reads everything from `reader` and returns a [`Source`] with the text, and no path.
+    ///
+    /// As with [`Source::from_file`], only IO or encoding errors will be returned, with
+    /// invalid UTF-8 reported as [`std::io::ErrorKind::InvalidData`].
+    pub fn from_reader(mut reader: impl Read) -> core::result::Result<Self, std::io::Error> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok(Source::new(IndexedString::from(text), None))
+    }
+
+    /// Returns the name of this [`Source`], if it has one.
+    ///
+    /// When a [`Source`] has no path, [`Diagnostic`]s about it are labeled with this name
+    /// (unless they have a context name of their own), instead of the default "input".
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns this [`Source`] with its name set to the given name, see [`Source::name`].
+    pub fn with_name(mut self, name: impl ToString) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Sets the name of this [`Source`], see [`Source::name`].
+    pub fn set_name(&mut self, name: Option<impl AsRef<str>>) {
+        self.name = name.map(|n| n.as_ref().to_string());
     }
 
     /// Sets the path of the file that this [`Source`] was read from.
@@ -55,6 +137,24 @@ impl Source {
     }
 }
 
+/// [`Source`]s are ordered by path, then name, then text, which is consistent with their
+/// [`PartialEq`] implementation.
+impl Ord for Source {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.path, &self.name, self.text.as_str()).cmp(&(
+            &other.path,
+            &other.name,
+            other.text.as_str(),
+        ))
+    }
+}
+
+impl PartialOrd for Source {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Deref for Source {
     type Target = IndexedString;
 
@@ -65,9 +165,130 @@ impl Deref for Source {
 
 impl<S: ToString> From<S> for Source {
     fn from(value: S) -> Self {
-        Source {
-            text: IndexedString::from(value.to_string()),
-            path: None,
+        Source::new(IndexedString::from(value.to_string()), None)
+    }
+}
+
+/// A [`Source`] is serialized as a struct with its `text`, `path` and `name`, e.g.
+/// `{"text":"a + b","path":null,"name":null}` in JSON. The line index is rebuilt when it is
+/// deserialized.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Source {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("Source", 3)?;
+        state.serialize_field("text", self.text.as_str())?;
+        state.serialize_field("path", &self.path)?;
+        state.serialize_field("name", &self.name)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Source {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> core::result::Result<Self, D::Error> {
+        use serde::de::{Error, MapAccess, SeqAccess, Visitor};
+
+        struct SourceVisitor;
+
+        impl<'de> Visitor<'de> for SourceVisitor {
+            type Value = Source;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("struct Source")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> core::result::Result<Source, A::Error> {
+                let text: String = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+                let path = seq.next_element()?.unwrap_or_default();
+                let mut source = Source::new(IndexedString::from(text), path);
+                source.name = seq.next_element()?.unwrap_or_default();
+                Ok(source)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> core::result::Result<Source, A::Error> {
+                let (mut text, mut path, mut name) = (None::<String>, None, None);
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "text" => text = Some(map.next_value()?),
+                        "path" => path = map.next_value()?,
+                        "name" => name = map.next_value()?,
+                        _ => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                let text = text.ok_or_else(|| A::Error::missing_field("text"))?;
+                let mut source = Source::new(IndexedString::from(text), path);
+                source.name = name;
+                Ok(source)
+            }
         }
+
+        deserializer.deserialize_struct("Source", &["text", "path", "name"], SourceVisitor)
     }
 }
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_source_serde() {
+    let source = Source::from_str("a\nb").with_name("config");
+    let json = serde_json::to_string(&source).unwrap();
+    assert_eq!(json, r#"{"text":"a\nb","path":null,"name":"config"}"#);
+    let parsed: Source = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, source);
+    assert_eq!(parsed.line_col(2), LineCol { line: 1, col: 0 });
+    let parsed: Source = serde_json::from_str(r#"{"text":"x"}"#).unwrap();
+    assert_eq!(parsed, Source::from_str("x"));
+    assert!(serde_json::from_str::<Source>(r#"{"name":"x"}"#).is_err());
+}
+
+#[test]
+fn test_source_line_col() {
+    let source = Source::from_str("ab\n\ncdé\nf");
+    assert_eq!(source.line_col(0), LineCol { line: 0, col: 0 });
+    assert_eq!(source.line_col(2), LineCol { line: 0, col: 2 });
+    assert_eq!(source.line_col(3), LineCol { line: 1, col: 0 });
+    assert_eq!(source.line_col(4), LineCol { line: 2, col: 0 });
+    assert_eq!(source.line_col(7), LineCol { line: 2, col: 3 });
+    assert_eq!(source.line_col(8), LineCol { line: 3, col: 0 });
+    assert_eq!(source.line_col(100), LineCol { line: 3, col: 1 });
+    assert_eq!(
+        Source::from_str("").line_col(0),
+        LineCol { line: 0, col: 0 }
+    );
+}
+
+#[test]
+fn test_source_from_text_and_path() {
+    let source = Source::from_text_and_path("a\nb", "virtual/doc.txt");
+    assert_eq!(source.source_text(), "a\nb");
+    assert_eq!(source.source_path(), Some(Path::new("virtual/doc.txt")));
+    assert_eq!(source.line_col(2), LineCol { line: 1, col: 0 });
+    let mut expected = Source::from_str("a\nb");
+    expected.set_path(Some("virtual/doc.txt"));
+    assert_eq!(source, expected);
+}
+
+#[test]
+fn test_source_from_reader() {
+    let source = Source::from_reader("héllo\nwörld".as_bytes()).unwrap();
+    assert_eq!(source.source_text(), "héllo\nwörld");
+    assert_eq!(source.source_path(), None);
+    assert_eq!(source, Source::from_str("héllo\nwörld"));
+    let err = Source::from_reader(&[0x68, 0xff, 0x69][..]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
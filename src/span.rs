@@ -1,6 +1,6 @@
 //! Home of [`Span`] and related types and traits.
 
-use std::{fmt::Display, ops::Range, path::Path, rc::Rc};
+use std::{cmp::Ordering, fmt::Display, ops::Range, path::Path, rc::Rc};
 
 use super::*;
 
@@ -62,7 +62,8 @@ impl Span {
         Span::new(Rc::new(Source::from_str("")), 0..0)
     }
 
-    /// Creates a new [`Span`] from a [`Source`] and a byte range.
+    /// Creates a new [`Span`] from a [`Source`] and a range of character indices (see
+    /// [`Span::byte_range`]). The end of the range is clamped to the length of the [`Source`].
     pub fn new(source: Rc<Source>, byte_range: Range<usize>) -> Self {
         let mut byte_range = byte_range;
         if source.len() > 0 && byte_range.end > source.len() {
@@ -77,7 +78,7 @@ impl Span {
     }
 
     /// Returns the text of the [`Source`] that this [`Span`] is associated with.
-    pub fn source_text(&self) -> IndexedSlice {
+    pub fn source_text(&self) -> IndexedSlice<'_> {
         self.source.slice(self.byte_range.clone())
     }
 
@@ -86,50 +87,102 @@ impl Span {
         self.source.source_path()
     }
 
-    /// Returns the byte range of this [`Span`], representing the start and end of the span
-    /// within the [`Source`].
+    /// Creates a new [`Span`] from a [`Source`] and a range of character indices.
     ///
-    /// Note that because of UTF-8, the start and end of the range may not correspond with the
-    /// start and end of a character in the source text.
+    /// Since [`Source`] is backed by an [`IndexedString`], which is indexed by character, this
+    /// is equivalent to [`Span::new`], but makes the intended units explicit at the call site.
+    pub fn from_char_range(source: Rc<Source>, char_range: Range<usize>) -> Self {
+        Span::new(source, char_range)
+    }
+
+    /// Returns the range of this [`Span`], representing the start and end of the span within
+    /// the [`Source`].
+    ///
+    /// Despite the name, this range is in terms of the _characters_ of the underlying
+    /// [`IndexedString`] rather than UTF-8 bytes, so it always falls on character boundaries.
+    /// It is identical to [`Span::char_range`].
     pub fn byte_range(&self) -> &Range<usize> {
         &self.byte_range
     }
 
+    /// Returns the range of character indices that this [`Span`] covers within its [`Source`].
+    ///
+    /// These indices can be used directly with [`IndexedStr::slice`] and
+    /// [`IndexedStr::char_at`] on the [`Source`].
+    pub fn char_range(&self) -> Range<usize> {
+        self.byte_range.clone()
+    }
+
     /// Returns the line and column of the start of this [`Span`] within the [`Source`].
     pub fn start(&self) -> LineCol {
-        let mut line = 0;
-        let mut col = 0;
-        for c in self.source.slice(0..self.byte_range.start).chars() {
-            if *c == '\n' {
-                col = 0;
-                line += 1;
-            } else {
-                col += 1;
-            }
-        }
-        LineCol { line, col }
+        self.source.line_col(self.byte_range.start)
     }
 
     /// Returns the line and column of the end of this [`Span`] within the [`Source`].
     pub fn end(&self) -> LineCol {
-        let LineCol { mut line, mut col } = self.start();
-        for c in self
+        self.source.line_col(self.byte_range.end)
+    }
+
+    /// Returns the line and column of the start of this [`Span`], where the column is the
+    /// _visual_ column obtained by expanding tabs to the next multiple of `tab_width` and
+    /// counting wide and zero-width characters as two and zero columns respectively.
+    ///
+    /// Use [`Span::start`] for the raw, character-based column.
+    pub fn visual_start(&self, tab_width: usize) -> LineCol {
+        self.visualize(self.start(), tab_width)
+    }
+
+    /// Returns the line and column of the end of this [`Span`], where the column is the
+    /// _visual_ column obtained by expanding tabs to the next multiple of `tab_width` and
+    /// counting wide and zero-width characters as two and zero columns respectively.
+    ///
+    /// Use [`Span::end`] for the raw, character-based column.
+    pub fn visual_end(&self, tab_width: usize) -> LineCol {
+        self.visualize(self.end(), tab_width)
+    }
+
+    fn visualize(&self, line_col: LineCol, tab_width: usize) -> LineCol {
+        let Some(start) = self.source.line_start(line_col.line) else {
+            return line_col;
+        };
+        let col = self
             .source
-            .slice(self.byte_range.start..self.byte_range.end)
+            .slice(start..start + line_col.col)
             .chars()
-        {
-            if *c == '\n' {
-                col = 0;
-                line += 1;
-            } else {
-                col += 1;
-            }
+            .iter()
+            .fold(0, |col, c| col + display_width(*c, col, tab_width));
+        LineCol {
+            line: line_col.line,
+            col,
         }
-        LineCol { line, col }
     }
 
     /// Returns an iterator over the lines of the [`Source`] that this [`Span`] is associated with,
-    pub fn source_lines(&self) -> impl Iterator<Item = (IndexedSlice, Range<usize>)> + '_ {
+    /// along with the range of characters within each line that the [`Span`] covers.
+    ///
+    /// See [`Span::source_line_numbers`] to also get the line number of each line.
+    pub fn source_lines(&self) -> impl Iterator<Item = (IndexedSlice<'_>, Range<usize>)> + '_ {
+        self.source_line_numbers()
+            .map(|(_, line, range)| (line, range))
+    }
+
+    /// Like [`Span::source_lines`], but also yields the 0-based line number of each line
+    /// within the [`Source`], as in [`LineCol::line`].
+    ///
+    /// ```
+    /// use quoth::*;
+    /// use std::rc::Rc;
+    ///
+    /// let span = Span::new(Rc::new(Source::from_str("a\nbcd\nef")), 3..7);
+    /// let lines: Vec<_> = span
+    ///     .source_line_numbers()
+    ///     .map(|(n, line, range)| (n, line.to_string(), range))
+    ///     .collect();
+    /// assert_eq!(lines, [(1, "bcd".to_string(), 1..3), (2, "ef".to_string(), 0..1)]);
+    /// ```
+    pub fn source_line_numbers(
+        &self,
+    ) -> impl Iterator<Item = (usize, IndexedSlice<'_>, Range<usize>)> + '_ {
         let start_line_col = self.start();
         let end_line_col = self.end();
         let start_col = start_line_col.col;
@@ -141,24 +194,34 @@ impl Span {
             .enumerate()
             .filter_map(move |(i, line)| {
                 let len = line.len();
-                if start_line == end_line && end_line == i {
-                    Some((line, start_col..end_col))
+                let range = if start_line == end_line && end_line == i {
+                    start_col..end_col
                 } else if i == start_line {
-                    Some((line, start_col..len))
+                    start_col..len
                 } else if i > start_line && i < end_line {
-                    Some((line, 0..len))
+                    0..len
                 } else if i == end_line {
-                    Some((line, 0..end_col))
+                    0..end_col
                 } else {
-                    None
-                }
+                    return None;
+                };
+                Some((i, line, range))
             })
     }
 
+    /// Returns `true` if this [`Span`] and `other` come from the same [`Source`].
+    ///
+    /// Spans almost always share the same [`Rc<Source>`], so the pointers are compared first
+    /// and the (linear time) comparison of the sources themselves is only done if they differ.
+    pub fn same_source(&self, other: &Span) -> bool {
+        Rc::ptr_eq(&self.source, &other.source) || self.source == other.source
+    }
+
     /// Joins this [`Span`] with another [`Span`], returning a new [`Span`] that encompasses both.
     ///
     /// If the two spans do not come from the same [`Source`], this method will return an error
-    /// unless one or more of the spans is [`Span::blank()`].
+    /// unless one or more of the spans is [`Span::blank()`]. Use [`Span::join_cross_source`]
+    /// to keep spans from different sources side by side instead.
     pub fn join(&self, other: &Span) -> core::result::Result<Span, SpanJoinError> {
         if self.source.is_empty() {
             return Ok(other.clone());
@@ -166,7 +229,7 @@ impl Span {
         if other.source.is_empty() {
             return Ok(self.clone());
         }
-        if self.source != other.source {
+        if !self.same_source(other) {
             return Err(SpanJoinError);
         }
         let start = self.byte_range.start.min(other.byte_range.start);
@@ -177,10 +240,285 @@ impl Span {
         })
     }
 
+    /// Joins all of the given [`Span`]s into a single [`Span`] that encompasses them, see
+    /// [`Span::join`].
+    ///
+    /// Blank spans are ignored, as with [`Span::join`]. Returns `Ok(None)` if `spans` is empty,
+    /// or a [`SpanJoinError`] if the spans do not all come from the same [`Source`].
+    pub fn union_all(
+        spans: impl IntoIterator<Item = Span>,
+    ) -> core::result::Result<Option<Span>, SpanJoinError> {
+        let mut spans = spans.into_iter();
+        let Some(first) = spans.next() else {
+            return Ok(None);
+        };
+        spans.try_fold(first, |acc, span| acc.join(&span)).map(Some)
+    }
+
+    /// Joins this [`Span`] with another [`Span`] that may come from a different [`Source`].
+    ///
+    /// Spans from the same [`Source`] (or blank spans) are joined as in [`Span::join`],
+    /// yielding a single [`Span`]. Otherwise both spans are kept as-is, one per [`Source`], so
+    /// that the result can be passed to anything accepting a [`MultiSpan`], e.g.
+    /// [`Diagnostic::with_spans`], which renders spans from other sources as separate blocks.
+    pub fn join_cross_source(&self, other: &Span) -> Vec<Span> {
+        match self.join(other) {
+            Ok(span) => vec![span],
+            Err(SpanJoinError) => vec![self.clone(), other.clone()],
+        }
+    }
+
     /// Returns whether this [`Span`] is blank, i.e. has a zero-length range.
     pub fn is_blank(&self) -> bool {
         self.byte_range.start == self.byte_range.end
     }
+
+    /// Returns a sub-span of this [`Span`] with leading and trailing whitespace (as defined by
+    /// [`char::is_whitespace`]) excluded.
+    ///
+    /// If the span consists entirely of whitespace, a zero-length span at its original start
+    /// is returned.
+    pub fn trim(&self) -> Span {
+        self.trim_start().trim_end()
+    }
+
+    /// Returns a sub-span of this [`Span`] with leading whitespace excluded, see [`Span::trim`].
+    pub fn trim_start(&self) -> Span {
+        let text = self.source_text();
+        match text.chars().iter().position(|c| !c.is_whitespace()) {
+            Some(skip) => self.with_range(self.byte_range.start + skip..self.byte_range.end),
+            None => self.with_range(self.byte_range.start..self.byte_range.start),
+        }
+    }
+
+    /// Returns a sub-span of this [`Span`] with trailing whitespace excluded, see [`Span::trim`].
+    pub fn trim_end(&self) -> Span {
+        let text = self.source_text();
+        match text.chars().iter().rposition(|c| !c.is_whitespace()) {
+            Some(last) => self.with_range(self.byte_range.start..self.byte_range.start + last + 1),
+            None => self.with_range(self.byte_range.start..self.byte_range.start),
+        }
+    }
+
+    /// Returns the [`Span`] covering the given range of character indices _relative to the
+    /// start of this [`Span`]_, e.g. of something found within [`Span::source_text`].
+    ///
+    /// The range is clamped to this [`Span`]. See also [`IndexedStrExt::range_within`] for
+    /// mapping an [`IndexedSlice`] back to its position in the [`Source`].
+    ///
+    /// ```
+    /// use quoth::*;
+    /// use std::rc::Rc;
+    ///
+    /// let span = Span::new(Rc::new(Source::from_str("let wörld = 1;")), 4..12);
+    /// let i = span.source_text().find("=").unwrap();
+    /// assert_eq!(span.subspan(i..i + 1).byte_range(), &(10..11));
+    /// ```
+    pub fn subspan(&self, range: Range<usize>) -> Span {
+        let Range { start, end } = self.byte_range;
+        let clamp = |i: usize| start.saturating_add(i).min(end);
+        self.with_range(clamp(range.start)..clamp(range.end.max(range.start)))
+    }
+
+    /// Returns a [`Span`] with the same start as this one, ending at the character index
+    /// `end`.
+    ///
+    /// `end` is clamped so that it is neither before the start of this [`Span`] nor past the
+    /// end of its [`Source`].
+    pub fn with_end(&self, end: usize) -> Span {
+        Span::new(
+            self.source.clone(),
+            self.byte_range.start..end.max(self.byte_range.start),
+        )
+    }
+
+    /// Returns a [`Span`] with the same start as this one, covering the next `n` characters
+    /// (or fewer if the end of the [`Source`] comes first).
+    pub fn with_len_chars(&self, n: usize) -> Span {
+        self.with_end(self.byte_range.start.saturating_add(n))
+    }
+
+    /// Extends this [`Span`] up to the end of `other`, which must not start before this
+    /// [`Span`] does.
+    ///
+    /// Unlike [`Span::join`], the result always starts where this [`Span`] starts. As with
+    /// [`Span::join`], blank spans are ignored and spans from different [`Source`]s result in
+    /// a [`SpanJoinError`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` starts before this [`Span`].
+    pub fn extend_to(&self, other: &Span) -> core::result::Result<Span, SpanJoinError> {
+        if self.source.is_empty() || other.source.is_empty() {
+            return self.join(other);
+        }
+        if !self.same_source(other) {
+            return Err(SpanJoinError);
+        }
+        assert!(
+            other.byte_range.start >= self.byte_range.start,
+            "cannot extend a span to one that starts before it"
+        );
+        Ok(self.with_end(other.byte_range.end))
+    }
+
+    /// Maps this [`Span`], which is expressed relative to a [`Source`] created from the text
+    /// of `onto`, back onto the [`Source`] of `onto` by offsetting it by the start of `onto`.
+    ///
+    /// This supports parsing a region of a file as its own [`Source`] (e.g. the body of a
+    /// string literal) while keeping diagnostics pointing at the original file. Blank spans are
+    /// returned unchanged, and a [`SpanJoinError`] is returned if this [`Span`] extends past
+    /// the length of `onto`, since it then can't have come from the text of `onto`.
+    ///
+    /// ```
+    /// use quoth::*;
+    /// use std::rc::Rc;
+    ///
+    /// let outer = Span::new(Rc::new(Source::from_str("let s = \"a + b\";")), 9..14);
+    /// let inner = Rc::new(Source::from_str(outer.source_text().as_str()));
+    /// let plus = Span::new(inner, 2..3);
+    /// let rebased = plus.rebase(&outer).unwrap();
+    /// assert_eq!(rebased.byte_range(), &(11..12));
+    /// assert_eq!(rebased.source_text(), "+");
+    /// ```
+    pub fn rebase(&self, onto: &Span) -> core::result::Result<Span, SpanJoinError> {
+        if self.source.is_empty() {
+            return Ok(self.clone());
+        }
+        if self.byte_range.end > onto.byte_range.len() {
+            return Err(SpanJoinError);
+        }
+        let start = onto.byte_range.start;
+        Ok(onto.with_range(start + self.byte_range.start..start + self.byte_range.end))
+    }
+
+    fn with_range(&self, byte_range: Range<usize>) -> Span {
+        Span {
+            source: self.source.clone(),
+            byte_range,
+        }
+    }
+
+    /// Returns `true` if `other` lies entirely within this [`Span`] and both come from the
+    /// same [`Source`].
+    ///
+    /// Unlike with [`Span::join`], [`Span::blank()`] is not treated as a wildcard: a [`Span`]
+    /// with an empty [`Source`] neither contains nor is contained by any other [`Span`].
+    /// Zero-length spans are contained by any [`Span`] that encloses their position,
+    /// including its end.
+    pub fn contains(&self, other: &Span) -> bool {
+        !self.source.is_empty()
+            && self.same_source(other)
+            && self.byte_range.start <= other.byte_range.start
+            && other.byte_range.end <= self.byte_range.end
+    }
+
+    /// Returns `true` if the given offset into the [`Source`] falls within this [`Span`].
+    ///
+    /// The range is half-open, so zero-length spans contain no offsets.
+    pub fn contains_offset(&self, byte: usize) -> bool {
+        self.byte_range.contains(&byte)
+    }
+
+    /// Returns the region where this [`Span`] overlaps `other`, or `None` if they do not
+    /// overlap or come from different [`Source`]s.
+    ///
+    /// Spans that merely touch (i.e. one ends where the other starts) do not overlap, unless
+    /// one of them is zero-length. As with [`Span::contains`], spans with an empty [`Source`]
+    /// never overlap anything.
+    pub fn intersect(&self, other: &Span) -> Option<Span> {
+        if self.source.is_empty() || !self.same_source(other) {
+            return None;
+        }
+        let start = self.byte_range.start.max(other.byte_range.start);
+        let end = self.byte_range.end.min(other.byte_range.end);
+        if start > end || (start == end && !self.is_blank() && !other.is_blank()) {
+            return None;
+        }
+        Some(Span {
+            source: self.source.clone(),
+            byte_range: start..end,
+        })
+    }
+}
+
+/// [`Span`]s from the same [`Source`] are ordered by the start and then the end of their
+/// ranges, so sorting them puts them in the order they appear in the source text.
+///
+/// [`Span`]s from different [`Source`]s are ordered by their [`Source`]s first (see the [`Ord`]
+/// implementation of [`Source`]), which groups them by source when sorted.
+impl Ord for Span {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let sources = match Rc::ptr_eq(&self.source, &other.source) {
+            true => Ordering::Equal,
+            false => self.source.cmp(&other.source),
+        };
+        sources.then_with(|| {
+            (self.byte_range.start, self.byte_range.end)
+                .cmp(&(other.byte_range.start, other.byte_range.end))
+        })
+    }
+}
+
+impl PartialOrd for Span {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Returns the number of terminal columns `c` occupies when displayed starting at column
+/// `col`, where tabs advance to the next multiple of `tab_width`.
+///
+/// East Asian wide and fullwidth characters (such as CJK ideographs, Hangul and most emoji)
+/// count as two columns, and combining marks, zero-width spaces and joiners and variation
+/// selectors count as zero columns, since they are drawn on top of the preceding character.
+pub(crate) fn display_width(c: char, col: usize, tab_width: usize) -> usize {
+    match c {
+        '\t' => {
+            let tab_width = tab_width.max(1);
+            tab_width - col % tab_width
+        }
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{200B}'..='\u{200D}'
+        | '\u{2060}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{FEFF}'
+        | '\u{E0100}'..='\u{E01EF}' => 0,
+        '\u{1100}'..='\u{115F}'
+        | '\u{231A}'..='\u{231B}'
+        | '\u{23E9}'..='\u{23EC}'
+        | '\u{2600}'..='\u{27BF}'
+        | '\u{2B50}'
+        | '\u{2B55}'
+        | '\u{2E80}'..='\u{303E}'
+        | '\u{3041}'..='\u{33FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{A000}'..='\u{A4CF}'
+        | '\u{AC00}'..='\u{D7A3}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FE30}'..='\u{FE4F}'
+        | '\u{FF00}'..='\u{FF60}'
+        | '\u{FFE0}'..='\u{FFE6}'
+        | '\u{1F004}'
+        | '\u{1F0CF}'
+        | '\u{1F18E}'
+        | '\u{1F191}'..='\u{1F19A}'
+        | '\u{1F1E6}'..='\u{1F1FF}'
+        | '\u{1F200}'..='\u{1F2FF}'
+        | '\u{1F300}'..='\u{1F64F}'
+        | '\u{1F680}'..='\u{1F6FF}'
+        | '\u{1F7E0}'..='\u{1F7EB}'
+        | '\u{1F900}'..='\u{1F9FF}'
+        | '\u{1FA70}'..='\u{1FAFF}'
+        | '\u{20000}'..='\u{2FFFD}'
+        | '\u{30000}'..='\u{3FFFD}' => 2,
+        _ => 1,
+    }
 }
 
 /// Represents a line and column within a [`Source`].
@@ -194,13 +532,180 @@ pub struct LineCol {
     pub col: usize,
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for LineCol {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("LineCol", 2)?;
+        state.serialize_field("line", &self.line)?;
+        state.serialize_field("col", &self.col)?;
+        state.end()
+    }
+}
+
+/// A plain-data view of the location of a [`Span`], suitable for handing off to external
+/// tooling (serializable when the `serde` feature is enabled).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SpanLocation {
+    /// The path of the [`Source`] if it was read from a file, otherwise the context name of
+    /// the input.
+    pub source: String,
+    /// The range of UTF-8 byte offsets that the [`Span`] covers within the text of its
+    /// [`Source`], which is what most external tooling expects.
+    pub byte_range: Range<usize>,
+    /// The range of character indices that the [`Span`] covers within its [`Source`], i.e.
+    /// [`Span::char_range`]. This differs from `byte_range` once the text before or within the
+    /// span contains non-ASCII characters.
+    pub char_range: Range<usize>,
+    /// The line and column of the start of the [`Span`].
+    pub start: LineCol,
+    /// The line and column of the end of the [`Span`].
+    pub end: LineCol,
+}
+
+impl Span {
+    /// Returns a [`SpanLocation`] describing where this [`Span`] is located, using
+    /// `context_name` as the name of the source if it was not read from a file.
+    pub fn location(&self, context_name: impl ToString) -> SpanLocation {
+        SpanLocation {
+            source: match self.source_path() {
+                Some(path) => path.display().to_string(),
+                None => context_name.to_string(),
+            },
+            byte_range: self
+                .source_text()
+                .byte_range_within(&**self.source)
+                .unwrap_or_default(),
+            char_range: self.char_range(),
+            start: self.start(),
+            end: self.end(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SpanLocation {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("SpanLocation", 5)?;
+        state.serialize_field("source", &self.source)?;
+        state.serialize_field("byte_range", &self.byte_range)?;
+        state.serialize_field("char_range", &self.char_range)?;
+        state.serialize_field("start", &self.start)?;
+        state.serialize_field("end", &self.end)?;
+        state.end()
+    }
+}
+
+/// A [`Span`] is serialized as a struct holding an inline copy of its [`Source`] and its
+/// `byte_range`, e.g. `{"source":{"text":"a + b","path":null,"name":null},"byte_range":
+/// {"start":4,"end":5}}` in JSON. Deserializing fails if the `byte_range` doesn't lie within
+/// the text of the [`Source`].
+///
+/// Sources are _not_ deduplicated: every serialized [`Span`] carries the full text of its
+/// [`Source`], and every deserialized [`Span`] gets its own [`Rc<Source>`] (which still
+/// compares equal to, and can be joined with, spans of an identical [`Source`]). When storing
+/// many spans from a large [`Source`], serialize the [`Source`] once alongside the
+/// [`Span::byte_range`]s instead and rebuild the spans with [`Span::new`], sharing one
+/// [`Rc`].
+#[cfg(feature = "serde")]
+impl serde::Serialize for Span {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("Span", 2)?;
+        state.serialize_field("source", self.source.as_ref())?;
+        state.serialize_field("byte_range", &self.byte_range)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Span {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> core::result::Result<Self, D::Error> {
+        use serde::de::{Error, MapAccess, SeqAccess, Visitor};
+
+        struct SpanVisitor;
+
+        /// Builds the [`Span`], rejecting ranges that don't fall within the source text.
+        fn checked_span<E: Error>(
+            source: Source,
+            byte_range: Range<usize>,
+        ) -> core::result::Result<Span, E> {
+            if byte_range.start > byte_range.end || byte_range.end > source.len() {
+                return Err(E::invalid_value(
+                    serde::de::Unexpected::Other(&format!(
+                        "range {}..{}",
+                        byte_range.start, byte_range.end
+                    )),
+                    &format!("a range within 0..{}", source.len()).as_str(),
+                ));
+            }
+            Ok(Span::new(Rc::new(source), byte_range))
+        }
+
+        impl<'de> Visitor<'de> for SpanVisitor {
+            type Value = Span;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("struct Span")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> core::result::Result<Span, A::Error> {
+                let source: Source = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+                let byte_range = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+                checked_span(source, byte_range)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> core::result::Result<Span, A::Error> {
+                let (mut source, mut byte_range) = (None::<Source>, None);
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "source" => source = Some(map.next_value()?),
+                        "byte_range" => byte_range = Some(map.next_value()?),
+                        _ => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                        }
+                    }
+                }
+                let source = source.ok_or_else(|| A::Error::missing_field("source"))?;
+                let byte_range = byte_range.ok_or_else(|| A::Error::missing_field("byte_range"))?;
+                checked_span(source, byte_range)
+            }
+        }
+
+        deserializer.deserialize_struct("Span", &["source", "byte_range"], SpanVisitor)
+    }
+}
+
 /// A trait for types that have a [`Span`].
 pub trait Spanned {
     /// Returns the underlying [`Span`] of self.
     ///
     /// If the type has multiple [`Span`]s, this method should return the primary [`Span`],
     /// i.e. by joining all of the [`Span`]s together, rather than storing a permanent primary
-    /// [`Span`] on the type directly.
+    /// [`Span`] on the type directly. `#[derive(Spanned)]` does this automatically for types
+    /// with several fields of type [`Span`].
     fn span(&self) -> Span;
 }
 
@@ -210,10 +715,115 @@ impl Spanned for Span {
     }
 }
 
+impl<T: Spanned + ?Sized> Spanned for Box<T> {
+    fn span(&self) -> Span {
+        T::span(self)
+    }
+}
+
+impl<T: Spanned + ?Sized> Spanned for Rc<T> {
+    fn span(&self) -> Span {
+        T::span(self)
+    }
+}
+
+/// Attaches a [`Span`] to a value of any type, e.g. a primitive, without having to write a
+/// dedicated newtype for it.
+///
+/// [`Sp`] dereferences to the wrapped value, and when `T` is [`Parsable`] so is [`Sp<T>`],
+/// with the [`Span`] set to the text consumed while parsing the `T`.
+///
+/// ```
+/// use quoth::{parsable::numbers::U64, *};
+///
+/// let Sp(n, span) = ParseStream::from("42").parse::<Sp<U64>>().unwrap();
+/// assert_eq!(n.value(), 42);
+/// assert_eq!(span.source_text(), "42");
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct Sp<T>(pub T, pub Span);
+
+impl<T> Sp<T> {
+    /// Creates a new [`Sp`] attaching the given [`Span`] to the given value.
+    pub fn new(value: T, span: Span) -> Self {
+        Sp(value, span)
+    }
+
+    /// Consumes this [`Sp`], discarding its [`Span`] and returning the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+
+    /// Transforms the wrapped value with `f`, keeping the same [`Span`].
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Sp<U> {
+        Sp(f(self.0), self.1)
+    }
+}
+
+impl<T> Spanned for Sp<T> {
+    fn span(&self) -> Span {
+        self.1.clone()
+    }
+}
+
+impl<T> core::ops::Deref for Sp<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> core::ops::DerefMut for Sp<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: Display> Display for Sp<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: Parsable> core::str::FromStr for Sp<T> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+impl<T: Parsable> Parsable for Sp<T> {
+    fn parse(stream: &mut ParseStream) -> Result<Self> {
+        let start_position = stream.position;
+        let value = stream.parse::<T>()?;
+        Ok(Sp(value, stream.span_from(start_position)))
+    }
+
+    fn parse_value(value: Self, stream: &mut ParseStream) -> Result<Self> {
+        let start_position = stream.position;
+        let value = stream.parse_value(value.0)?;
+        Ok(Sp(value, stream.span_from(start_position)))
+    }
+
+    fn unparse(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.unparse(f)
+    }
+}
+
 /// A trait for types that have multiple [`Span`]s.
 pub trait MultiSpan {
     /// Converts self into a vector of [`Span`]s.
     fn into_spans(self) -> Vec<Span>;
+
+    /// Joins the [`Span`]s of self into a single [`Span`], see [`Span::union_all`].
+    fn merged(self) -> core::result::Result<Option<Span>, SpanJoinError>
+    where
+        Self: Sized,
+    {
+        Span::union_all(self.into_spans())
+    }
 }
 
 impl MultiSpan for Vec<Span> {
@@ -221,3 +831,370 @@ impl MultiSpan for Vec<Span> {
         self
     }
 }
+
+impl<const N: usize> MultiSpan for [Span; N] {
+    fn into_spans(self) -> Vec<Span> {
+        self.into()
+    }
+}
+
+impl MultiSpan for Span {
+    fn into_spans(self) -> Vec<Span> {
+        vec![self]
+    }
+}
+
+#[test]
+fn test_derive_spanned_field_detection() {
+    use crate as quoth;
+
+    type Alias = Span;
+
+    #[derive(Spanned)]
+    struct NotSpan(Vec<Span>, quoth::Span);
+
+    #[derive(Spanned)]
+    struct Marked {
+        _open: Span,
+        #[quoth(span)]
+        body: Span,
+    }
+
+    #[derive(Spanned)]
+    struct Aliased {
+        _len: usize,
+        span: Alias,
+    }
+
+    let source = Rc::new(Source::from_str("hello world"));
+    let hello = Span::new(source.clone(), 0..5);
+    let world = Span::new(source.clone(), 6..11);
+    let parsed = NotSpan(vec![world.clone()], hello.clone());
+    assert_eq!(parsed.span(), hello);
+    assert_eq!(parsed.0.len(), 1);
+    let parsed = Marked {
+        _open: hello.clone(),
+        body: world.clone(),
+    };
+    assert_eq!(parsed.span(), world);
+    let parsed = Aliased {
+        _len: 5,
+        span: hello.clone(),
+    };
+    assert_eq!(parsed.span(), hello);
+}
+
+#[test]
+fn test_derive_spanned_joins_spans() {
+    use crate as quoth;
+
+    #[derive(Spanned)]
+    struct Parens {
+        open: Span,
+        _len: usize,
+        close: quoth::Span,
+    }
+
+    #[derive(Spanned)]
+    struct Primary {
+        _open: Span,
+        #[quoth(primary)]
+        close: Span,
+    }
+
+    #[derive(Spanned)]
+    enum Node {
+        Group(Span, Span),
+        Leaf(Span),
+    }
+
+    let source = Rc::new(Source::from_str("(a b)"));
+    let open = Span::new(source.clone(), 0..1);
+    let close = Span::new(source.clone(), 4..5);
+    let parens = Parens {
+        open: open.clone(),
+        _len: 2,
+        close: close.clone(),
+    };
+    assert_eq!(parens.span().source_text(), "(a b)");
+    assert_eq!(parens.open.byte_range(), &(0..1));
+    let primary = Primary {
+        _open: open.clone(),
+        close: close.clone(),
+    };
+    assert_eq!(primary.span(), close);
+    let cross = Span::new(Rc::new(Source::from_str("x")), 0..1);
+    assert_eq!(Node::Group(close.clone(), cross).span(), close);
+    assert_eq!(
+        Node::Group(close, open.clone()).span().byte_range(),
+        &(0..5)
+    );
+    assert_eq!(Node::Leaf(open.clone()).span(), open);
+}
+
+#[test]
+fn test_span_location() {
+    let source = Rc::new(Source::from_str("héllo\nwörld"));
+    let location = Span::new(source.clone(), 7..10).location("input");
+    assert_eq!(location.source, "input");
+    assert_eq!(location.byte_range, 8..12);
+    assert_eq!(location.char_range, 7..10);
+    assert_eq!(location.start, LineCol { line: 1, col: 1 });
+    assert_eq!(location.end, LineCol { line: 1, col: 4 });
+    let location = Span::new(source, 0..2).location("input");
+    assert_eq!(location.byte_range, 0..3);
+}
+
+#[test]
+fn test_span_visual_start_end() {
+    let source = Rc::new(Source::from_str("a\n\tb\tc"));
+    let span = Span::new(source, 3..6);
+    assert_eq!(span.source_text(), "b\tc");
+    assert_eq!(span.start(), LineCol { line: 1, col: 1 });
+    assert_eq!(span.end(), LineCol { line: 1, col: 4 });
+    assert_eq!(span.visual_start(4), LineCol { line: 1, col: 4 });
+    assert_eq!(span.visual_end(4), LineCol { line: 1, col: 9 });
+    assert_eq!(span.visual_start(8), LineCol { line: 1, col: 8 });
+    assert_eq!(span.visual_end(8), LineCol { line: 1, col: 17 });
+}
+
+#[test]
+fn test_span_contains_and_intersect() {
+    let source = Rc::new(Source::from_str("let value = 42;"));
+    let other = Rc::new(Source::from_str("let value = 42;!"));
+    let stmt = Span::new(source.clone(), 0..15);
+    let value = Span::new(source.clone(), 4..9);
+    let rhs = Span::new(source.clone(), 8..14);
+    assert!(stmt.contains(&value));
+    assert!(!value.contains(&stmt));
+    assert!(stmt.contains(&stmt));
+    assert!(value.contains(&Span::new(source.clone(), 9..9)));
+    assert!(!stmt.contains(&Span::new(other.clone(), 4..9)));
+    assert!(!stmt.contains(&Span::blank()));
+    assert!(!Span::blank().contains(&Span::blank()));
+
+    assert!(value.contains_offset(4));
+    assert!(value.contains_offset(8));
+    assert!(!value.contains_offset(9));
+    assert!(!Span::new(source.clone(), 4..4).contains_offset(4));
+
+    assert_eq!(value.intersect(&rhs).unwrap().source_text(), "e");
+    assert_eq!(stmt.intersect(&value), Some(value.clone()));
+    assert_eq!(value.intersect(&Span::new(source.clone(), 9..12)), None);
+    assert_eq!(
+        value.intersect(&Span::new(source.clone(), 9..9)),
+        Some(Span::new(source.clone(), 9..9))
+    );
+    assert_eq!(value.intersect(&Span::new(other, 4..9)), None);
+    assert_eq!(value.intersect(&Span::blank()), None);
+}
+
+#[test]
+fn test_span_char_range() {
+    let source = Rc::new(Source::from_str("héllo, wörld"));
+    let span = Span::from_char_range(source.clone(), 7..12);
+    assert_eq!(span.char_range(), 7..12);
+    assert_eq!(span.source_text(), "wörld");
+    assert_eq!(source.slice(span.char_range()), "wörld");
+    assert_eq!(source.char_at(span.char_range().start + 1), Some('ö'));
+    assert_eq!(span, Span::new(source, 7..12));
+}
+
+#[test]
+fn test_span_trim() {
+    let source = Rc::new(Source::from_str("let x =  \t välue \n;"));
+    let span = Span::new(source.clone(), 7..18);
+    assert_eq!(span.source_text(), "  \t välue \n");
+    assert_eq!(span.trim().source_text(), "välue");
+    assert_eq!(span.trim().byte_range(), &(11..16));
+    assert_eq!(span.trim_start().source_text(), "välue \n");
+    assert_eq!(span.trim_end().source_text(), "  \t välue");
+    let blank = Span::new(source.clone(), 7..10);
+    assert_eq!(blank.trim(), Span::new(source.clone(), 7..7));
+    assert_eq!(blank.trim_start(), Span::new(source.clone(), 7..7));
+    assert_eq!(blank.trim_end(), Span::new(source.clone(), 7..7));
+    assert_eq!(
+        Span::new(source.clone(), 0..3).trim(),
+        Span::new(source, 0..3)
+    );
+}
+
+#[test]
+fn test_span_subspan() {
+    let source = Rc::new(Source::from_str("héllo, wörld"));
+    let span = Span::new(source.clone(), 7..12);
+    assert_eq!(span.subspan(1..3).source_text(), "ör");
+    assert_eq!(span.subspan(3..100), Span::new(source.clone(), 10..12));
+    assert_eq!(span.subspan(100..200), Span::new(source.clone(), 12..12));
+    let text = span.source_text();
+    let word = text.slice(1..3);
+    let range = word.range_within(&source.source_text()).unwrap();
+    assert_eq!(Span::new(source, range), span.subspan(1..3));
+}
+
+#[test]
+fn test_span_derived_spans() {
+    let source = Rc::new(Source::from_str("héllo, wörld"));
+    let span = Span::new(source.clone(), 1..3);
+    assert_eq!(span.with_end(5).source_text(), "éllo");
+    assert_eq!(span.with_end(0), Span::new(source.clone(), 1..1));
+    assert_eq!(span.with_end(100).source_text(), "éllo, wörld");
+    assert_eq!(span.with_len_chars(1).source_text(), "é");
+    assert_eq!(span.with_len_chars(usize::MAX).byte_range(), &(1..12));
+    let other = Span::new(source.clone(), 7..9);
+    assert_eq!(span.extend_to(&other).unwrap().source_text(), "éllo, wö");
+    assert_eq!(span.extend_to(&Span::blank()).unwrap(), span);
+    let foreign = Span::new(Rc::new(Source::from_str("xyz")), 2..3);
+    assert_eq!(span.extend_to(&foreign), Err(SpanJoinError));
+    let inner = Span::new(source, 1..2);
+    assert_eq!(span.extend_to(&inner).unwrap().byte_range(), &(1..2));
+}
+
+#[test]
+#[should_panic(expected = "starts before it")]
+fn test_span_extend_to_earlier_span() {
+    let source = Rc::new(Source::from_str("abc"));
+    let _ = Span::new(source.clone(), 1..2).extend_to(&Span::new(source, 0..1));
+}
+
+#[test]
+fn test_span_rebase() {
+    let outer_source = Rc::new(Source::from_str("x = \"héllo {wörld}\""));
+    let outer = Span::new(outer_source.clone(), 5..18);
+    let inner = Rc::new(Source::from_str(outer.source_text().as_str()));
+    let word = Span::new(inner.clone(), 7..12);
+    assert_eq!(word.source_text(), "wörld");
+    let rebased = word.rebase(&outer).unwrap();
+    assert_eq!(rebased, Span::new(outer_source, 12..17));
+    assert_eq!(rebased.source_text(), "wörld");
+    assert!(Span::new(inner, 12..13)
+        .rebase(&outer.subspan(0..5))
+        .is_err());
+    assert!(Span::blank().rebase(&outer).unwrap().is_blank());
+}
+
+#[test]
+fn test_span_join_cross_source() {
+    let main = Rc::new(Source::from_str("include!(\"other\");"));
+    let other = Rc::new(Source::from_str("fn included() {}"));
+    let a = Span::new(main.clone(), 0..8);
+    let b = Span::new(main.clone(), 9..16);
+    assert_eq!(
+        a.join_cross_source(&b),
+        vec![Span::new(main.clone(), 0..16)]
+    );
+    assert_eq!(a.join_cross_source(&Span::blank()), vec![a.clone()]);
+    let c = Span::new(other, 3..11);
+    assert_eq!(a.join_cross_source(&c), vec![a.clone(), c.clone()]);
+}
+
+#[test]
+fn test_span_ord() {
+    let a = Rc::new(Source::from_str("let x = 1;"));
+    let mut b = Source::from_str("let x = 1;");
+    b.set_path(Some("b.rs"));
+    let b = Rc::new(b);
+    let mut spans = [
+        Span::new(b.clone(), 0..3),
+        Span::new(a.clone(), 4..5),
+        Span::new(a.clone(), 0..10),
+        Span::new(a.clone(), 0..3),
+        Span::new(b.clone(), 8..9),
+    ];
+    spans.sort();
+    let ranges: Vec<_> = spans
+        .iter()
+        .map(|span| (span.source_path().is_some(), span.byte_range().clone()))
+        .collect();
+    assert_eq!(
+        ranges,
+        [
+            (false, 0..3),
+            (false, 0..10),
+            (false, 4..5),
+            (true, 0..3),
+            (true, 8..9)
+        ]
+    );
+    let copy = Rc::new(Source::from_str("let x = 1;"));
+    assert_eq!(
+        Span::new(a.clone(), 4..5).cmp(&Span::new(copy.clone(), 4..5)),
+        Ordering::Equal
+    );
+    assert_eq!(Span::new(a, 4..5), Span::new(copy, 4..5));
+}
+
+#[test]
+fn test_sp() {
+    use parsable::*;
+
+    let mut stream = ParseStream::from("  hey");
+    let parsed = stream.parse::<Sp<Optional<Whitespace>>>().unwrap();
+    assert!(parsed.is_some());
+    assert_eq!(parsed.span().byte_range(), &(0..2));
+    let parsed = stream.parse::<Sp<Optional<Whitespace>>>().unwrap();
+    assert!(parsed.is_none());
+    assert_eq!(parsed.1.byte_range(), &(2..2));
+    let parsed = stream.parse_value(Sp::new(Exact::from("hey"), Span::blank()));
+    assert_eq!(parsed.unwrap().to_string(), "hey");
+    let word = Sp::new("word", Span::blank()).map(str::len);
+    assert_eq!(*word, 4);
+    assert_eq!(word.into_inner(), 4);
+}
+
+#[test]
+fn test_span_same_source() {
+    let source = Rc::new(Source::from_str("abc"));
+    let a = Span::new(source.clone(), 0..1);
+    assert!(a.same_source(&Span::new(source, 2..3)));
+    assert!(a.same_source(&Span::new(Rc::new(Source::from_str("abc")), 0..1)));
+    assert!(!a.same_source(&Span::new(Rc::new(Source::from_str("abd")), 0..1)));
+}
+
+#[test]
+fn test_span_union_all() {
+    let source = Rc::new(Source::from_str("hello world"));
+    let spans = [
+        Span::new(source.clone(), 6..8),
+        Span::blank(),
+        Span::new(source.clone(), 2..3),
+    ];
+    let merged = Span::union_all(spans.clone()).unwrap().unwrap();
+    assert_eq!(merged.byte_range(), &(2..8));
+    assert_eq!(spans.merged(), Ok(Some(merged)));
+    assert_eq!(Span::union_all([]), Ok(None));
+    assert_eq!(Vec::new().merged(), Ok(None));
+    let other = Span::new(Rc::new(Source::from_str("other")), 0..1);
+    assert_eq!(
+        vec![Span::new(source, 0..1), other].merged(),
+        Err(SpanJoinError)
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_span_serde() {
+    let source = Rc::new(Source::from_str("a + b"));
+    let span = Span::new(source.clone(), 4..5);
+    let json = serde_json::to_string(&span).unwrap();
+    assert_eq!(
+        json,
+        r#"{"source":{"text":"a + b","path":null,"name":null},"byte_range":{"start":4,"end":5}}"#
+    );
+    let parsed: Span = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, span);
+    assert_eq!(parsed.source_text(), "b");
+    let joined = parsed.join(&Span::new(source, 0..1)).unwrap();
+    assert_eq!(joined.source_text(), "a + b");
+    let err = serde_json::from_str::<Span>(r#"{"byte_range":{"start":0,"end":1}}"#);
+    assert!(err.is_err());
+    for byte_range in [r#"{"start":5,"end":9}"#, r#"{"start":2,"end":1}"#] {
+        let json = format!(r#"{{"source":{{"text":"ab"}},"byte_range":{byte_range}}}"#);
+        let err = serde_json::from_str::<Span>(&json).unwrap_err();
+        assert!(err.to_string().contains("a range within 0..2"));
+    }
+    let json = r#"{"source":{"text":""},"byte_range":{"start":0,"end":1}}"#;
+    assert!(serde_json::from_str::<Span>(json).is_err());
+    let json = serde_json::to_string(&Span::blank()).unwrap();
+    assert!(serde_json::from_str::<Span>(&json).unwrap().is_blank());
+}